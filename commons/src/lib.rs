@@ -15,9 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod span;
+pub mod symbol;
 pub mod types;
 
 pub use span::*;
+pub use symbol::*;
 pub use types::*;
 
 use derive_more::{Constructor, Display};
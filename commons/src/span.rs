@@ -53,9 +53,7 @@ impl ParserSpan for Span {
         Self::new(range.start, range.end - range.start)
     }
 
-    fn context(&self) -> Self::Context {
-        ()
-    }
+    fn context(&self) -> Self::Context {}
 
     fn start(&self) -> Self::Offset {
         self.offset
@@ -72,14 +70,14 @@ impl From<Range<usize>> for Span {
     }
 }
 
-impl Into<Range<usize>> for Span {
-    fn into(self) -> Range<usize> {
-        self.offset..self.offset + self.length
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.offset..span.offset + span.length
     }
 }
 
-impl Into<SourceSpan> for Span {
-    fn into(self) -> SourceSpan {
-        SourceSpan::new(self.offset.into(), self.length.into())
+impl From<Span> for SourceSpan {
+    fn from(span: Span) -> Self {
+        SourceSpan::new(span.offset.into(), span.length.into())
     }
 }
@@ -0,0 +1,122 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, sync::OnceLock};
+
+use lasso::{Key, Spur, ThreadedRodeo};
+
+/// Interned string which is cheap to copy, compare and hash, it lives until the program ends
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+/// Declares the symbols that are interned before any other so they are known at compile time
+macro_rules! symbols {
+    ($($name:ident: $string:literal),* $(,)?) => {
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #[repr(u32)]
+        enum Predefined {
+            $($name),*
+        }
+
+        /// Pre-interned symbols
+        pub mod sym {
+            use super::*;
+
+            $(pub const $name: Symbol = Symbol(Predefined::$name as u32);)*
+        }
+
+        const PREDEFINED: &[&str] = &[$($string),*];
+    };
+}
+
+symbols! {
+    PUB: "pub",
+    MOD: "mod",
+    USE: "use",
+    CONST: "const",
+    TYPE: "type",
+    IMPL: "impl",
+    TRAIT: "trait",
+    SELF_TY: "Self",
+    SELF: "self",
+    FN: "fn",
+    MOVE: "move",
+    LET: "let",
+    MUT: "mut",
+    MATCH: "match",
+    AS: "as",
+    LOOP: "loop",
+    WHILE: "while",
+    IF: "if",
+    ELSE: "else",
+    FOR: "for",
+    IN: "in",
+    CONTINUE: "continue",
+    BREAK: "break",
+    BOOL: "bool",
+    FN_TY: "Fn",
+    RETURN: "return",
+    TRUE: "true",
+    FALSE: "false",
+}
+
+/// Interner shared by every thread, which begins with the predefined symbols
+fn interner() -> &'static ThreadedRodeo {
+    static INTERNER: OnceLock<ThreadedRodeo> = OnceLock::new();
+    INTERNER.get_or_init(|| {
+        let interner = ThreadedRodeo::new();
+        PREDEFINED.iter().for_each(|string| {
+            interner.get_or_intern_static(string);
+        });
+        interner
+    })
+}
+
+impl Symbol {
+    /// Interns the string, the same symbol is returned for equal strings
+    pub fn intern(string: &str) -> Self {
+        Self(interner().get_or_intern(string).into_usize() as u32)
+    }
+
+    /// Interned string
+    pub fn as_str(&self) -> &'static str {
+        interner().resolve(&Spur::try_from_usize(self.0 as usize).unwrap())
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(string: &str) -> Self {
+        Symbol::intern(string)
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
@@ -16,14 +16,17 @@
 
 use crate::*;
 
-#[derive(Clone, PartialEq, Display, Constructor, Debug)]
+use getset::Getters;
+
+#[derive(Copy, Clone, PartialEq, Display, Constructor, Getters, Debug)]
 #[display(fmt = "signed: {} & size: {}", signed, size)]
+#[get = "pub"]
 pub struct Number {
     signed: bool,
     size: Size,
 }
 
-#[derive(Clone, PartialEq, Display, Debug)]
+#[derive(Copy, Clone, PartialEq, Display, Debug)]
 pub enum Size {
     _8,
     _16,
@@ -31,6 +34,18 @@ pub enum Size {
     _64,
 }
 
+impl Size {
+    /// Amount of bits of the size
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::_8 => 8,
+            Self::_16 => 16,
+            Self::_32 => 32,
+            Self::_64 => 64,
+        }
+    }
+}
+
 impl Default for Number {
     fn default() -> Self {
         Self::new(false, Size::_8)
@@ -24,10 +24,71 @@ pub enum Error {
     #[error("Unexpected token {}", .found)]
     #[diagnostic(code(kiban::parser))]
     Parser {
-        found: CompactString,
+        found: Symbol,
         #[help]
         help: Option<CompactString>,
         #[label = "unexpected"]
         span: Option<Span>,
     },
+    #[error("Unknown character {:?}", .found)]
+    #[diagnostic(
+        code(kiban::lexer),
+        help("This character does not begin any token, it is only allowed within literals and comments")
+    )]
+    Unknown {
+        found: char,
+        #[label = "unknown"]
+        span: Option<Span>,
+    },
+    #[error("Unterminated {}", .found)]
+    #[diagnostic(code(kiban::lexer))]
+    Unterminated {
+        found: CompactString,
+        #[label = "starts here"]
+        span: Option<Span>,
+    },
+    #[error("Invalid escape sequence {}", .found)]
+    #[diagnostic(code(kiban::lexer))]
+    Escape {
+        found: CompactString,
+        #[help]
+        help: Option<CompactString>,
+        #[label = "invalid escape"]
+        span: Option<Span>,
+    },
+    #[error("Invalid literal {}", .found)]
+    #[diagnostic(code(kiban::lexer))]
+    Literal {
+        found: CompactString,
+        #[help]
+        help: Option<CompactString>,
+        #[label = "invalid"]
+        span: Option<Span>,
+    },
+}
+
+impl Error {
+    /// Location of the error within the source
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Parser { span, .. }
+            | Self::Unknown { span, .. }
+            | Self::Unterminated { span, .. }
+            | Self::Escape { span, .. }
+            | Self::Literal { span, .. } => *span,
+        }
+    }
+
+    /// Moves the error by the given amount of bytes, as when text before it was edited
+    pub fn shift(mut self, by: isize) -> Self {
+        let (Self::Parser { span, .. }
+        | Self::Unknown { span, .. }
+        | Self::Unterminated { span, .. }
+        | Self::Escape { span, .. }
+        | Self::Literal { span, .. }) = &mut self;
+        if let Some(span) = span {
+            *span = Span::new(span.offset().wrapping_add_signed(by), *span.length());
+        }
+        self
+    }
 }
@@ -0,0 +1,50 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use kiban_lexer::TokenStream;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const SOURCE: &str = r##"/// Sums the values of a list
+pub fn sum(values: &[i64]) -> i64 {
+    let mut total = 0_i64;
+    for value in values {
+        total += *value; // accumulate
+    }
+    /* nested /* block */ comment */
+    total
+}
+
+const GREETING: &str = "こんにちは, \"world\"\n";
+const PATTERN: &str = r#"^\d{4}-\d{2}$"#;
+let ratio = 1.5e-3f64 * 0xFF_u32 as f64 << 2;
+"##;
+
+fn lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    for size in [1_usize << 20, 4 << 20] {
+        let input = SOURCE.repeat(size / SOURCE.len());
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.sample_size(10);
+        group.bench_function(format!("{} MiB", size >> 20), |b| {
+            b.iter(|| TokenStream::new(black_box(&input)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lexer);
+criterion_main!(benches);
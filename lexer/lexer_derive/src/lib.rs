@@ -14,80 +14,187 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Expr};
+use std::collections::{BTreeMap, HashMap};
 
-#[proc_macro_derive(TokenParser, attributes(token))]
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::quote;
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Expr, Lit, LitStr, Meta};
+
+/// Derives the lexeme parser and origin of enums whose variants are tagged with `#[token = "..."]`
+///
+/// Tokens are matched on a trie of their bytes, so the longest token wins regardless of the order of the variants.
+/// Enums tagged with `#[word]` only match their tokens as whole words, so they are never a prefix of an identifier.
+/// Variants sharing the same token are rejected, as are empty tokens as they would be a prefix of every other token
+///
+/// ```compile_fail
+/// use kiban_lexer_derive::TokenParser;
+///
+/// #[derive(TokenParser)]
+/// enum Operator {
+///     #[token = "+"]
+///     Plus,
+///     #[token = "+"]
+///     Add,
+/// }
+/// ```
+#[proc_macro_derive(TokenParser, attributes(token, word))]
 pub fn derive_token_parser(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
-    let mut refs = quote! {};
-    let mut repr = quote! {};
-    let mut alts = quote! {};
-    if let Data::Enum(DataEnum { variants, .. }) = data {
-        for variant in variants {
-            let mut attrs = variant.attrs.iter();
-            let (field, token) = (
-                variant.ident.to_token_stream(),
-                loop {
-                    let (path, value) = match &attrs.next().unwrap().meta {
-                        syn::Meta::List(list) => (&list.path, list.parse_args::<Expr>().unwrap()),
-                        syn::Meta::NameValue(named) => (&named.path, named.value.clone()),
-                        _ => panic!("Unexpected attribute format!"),
-                    };
-                    if path.is_ident("token") {
-                        break match value {
-                            syn::Expr::Lit(literal) => literal.lit.to_token_stream(),
-                            _ => panic!("Token is not a literal string!"),
-                        };
-                    }
-                },
-            );
-            refs.extend(
-                TokenStream::from(quote! {
-                    paste::paste! {
-                        pub const [<#field:snake:upper>]: crate::TokenKind = crate::TokenKind::#ident(#ident::#field);
-                    }
-                })
-                .into_iter(),
-            );
-            repr.extend(
-                TokenStream::from(quote! {
-                    Self::#field => #token,
-                })
-                .into_iter(),
-            );
-            alts.extend(
-                TokenStream::from(quote! {
-                    if let Some(span) = s.consume_pattern(#token) {
-                        return Some(crate::Token::new(crate::TokenKind::#ident(Self::#field), span));
-                    }
-                })
-                .into_iter(),
-            )
-        }
+    let DeriveInput {
+        ident, data, attrs, ..
+    } = parse_macro_input!(input);
+    let consume = if attrs.iter().any(|attr| attr.path().is_ident("word")) {
+        quote! { consume_word }
     } else {
+        quote! { consume_pattern }
+    };
+    let Data::Enum(DataEnum { variants, .. }) = data else {
         panic!("Token parser can only be derived on enums!")
+    };
+    let mut tokens: Vec<(LitStr, Ident)> = Vec::new();
+    let mut seen = HashMap::new();
+    for variant in variants {
+        let token = variant
+            .attrs
+            .iter()
+            .find_map(|attr| match &attr.meta {
+                Meta::NameValue(named) if named.path.is_ident("token") => Some(named.value.clone()),
+                Meta::List(list) if list.path.is_ident("token") => {
+                    Some(list.parse_args::<Expr>().unwrap())
+                }
+                _ => None,
+            })
+            .expect("Every variant needs a token!");
+        let token = match token {
+            Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(string),
+                ..
+            }) => string,
+            _ => panic!("Token is not a literal string!"),
+        };
+        let error = if token.value().is_empty() {
+            Some(format!(
+                "The token of `{}` is empty, so it would shadow every other token",
+                variant.ident
+            ))
+        } else {
+            seen.insert(token.value(), variant.ident.to_string())
+                .map(|previous| {
+                    format!(
+                        "The token {:?} of `{}` is already the token of `{}`",
+                        token.value(),
+                        variant.ident,
+                        previous
+                    )
+                })
+        };
+        if let Some(error) = error {
+            return syn::Error::new_spanned(&token, error)
+                .to_compile_error()
+                .into();
+        }
+        tokens.push((token, variant.ident));
     }
+    let (strings, fields): (Vec<_>, Vec<_>) = tokens.iter().cloned().unzip();
+    let trie = trie(
+        &tokens
+            .iter()
+            .map(|(token, field)| (token.value().into_bytes(), field))
+            .collect::<Vec<_>>(),
+        0,
+    );
     let output = quote! {
-        #refs
+        paste::paste! {
+            #(pub const [<#fields:snake:upper>]: crate::TokenKind = crate::TokenKind::#ident(#ident::#fields);)*
+        }
+        impl #ident {
+            /// Every variant in declaration order
+            pub const ALL: &'static [Self] = &[#(Self::#fields),*];
+
+            /// Token of the variant
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    #(Self::#fields => #strings,)*
+                }
+            }
+        }
+        impl std::str::FromStr for #ident {
+            type Err = ();
+
+            /// Finds the variant whose token is the whole string
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    #(#strings => Ok(Self::#fields),)*
+                    _ => Err(()),
+                }
+            }
+        }
         impl<'i> crate::Lexeme<'i> for #ident {
             fn parse(s: &mut crate::Fragment) -> Option<crate::Token<'i>> {
-                #alts
-                None
+                let bytes = s.ptr().as_bytes();
+                let mut matched: Option<Self> = None;
+                #trie
+                let variant = matched?;
+                s.#consume(variant.as_str())
+                    .map(|span| crate::Token::new(crate::TokenKind::#ident(variant), span))
             }
         }
         impl crate::TokenOrigin for #ident {
-            fn origin(&self) -> Option<compact_str::CompactString> {
-                Some(
-                    compact_str::CompactString::from(
-                        match self {
-                            #repr
-                        }
-                    )
-                )
+            fn origin(&self) -> Option<crate::Symbol> {
+                Some(crate::Symbol::intern(self.as_str()))
             }
         }
     };
     output.into()
 }
+
+/// Generates a match on the byte at the depth for the tokens sharing the bytes before it, which records the longest token matched so far
+fn trie(tokens: &[(Vec<u8>, &Ident)], depth: usize) -> TokenStream {
+    let mut branches: BTreeMap<u8, Vec<(Vec<u8>, &Ident)>> = BTreeMap::new();
+    for (token, field) in tokens.iter().filter(|(token, _)| token.len() > depth) {
+        branches
+            .entry(token[depth])
+            .or_default()
+            .push((token.clone(), field));
+    }
+    if branches.is_empty() {
+        return quote! {};
+    }
+    let arms = branches.iter().map(|(byte, tokens)| {
+        let byte = Literal::u8_suffixed(*byte);
+        let ends = tokens
+            .iter()
+            .find(|(token, _)| token.len() == depth + 1)
+            .map(|(_, field)| quote! { matched = Some(Self::#field); });
+        let next = trie(tokens, depth + 1);
+        quote! {
+            Some(#byte) => {
+                #ends
+                #next
+            }
+        }
+    });
+    quote! {
+        match bytes.get(#depth) {
+            #(#arms)*
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trie_does_not_depend_on_the_order_of_the_tokens() {
+        let idents = ["Dot", "DotDot", "DotDotEq", "Eq"]
+            .map(|name| Ident::new(name, proc_macro2::Span::call_site()));
+        let tokens = [".", "..", "..=", "="]
+            .iter()
+            .zip(&idents)
+            .map(|(token, ident)| (token.as_bytes().to_vec(), ident))
+            .collect::<Vec<_>>();
+        let reversed = tokens.iter().rev().cloned().collect::<Vec<_>>();
+        assert_eq!(trie(&tokens, 0).to_string(), trie(&reversed, 0).to_string());
+    }
+}
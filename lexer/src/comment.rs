@@ -16,6 +16,8 @@
 
 use crate::*;
 
+use std::collections::BTreeMap;
+
 #[derive(Copy, Clone, Constructor, PartialEq, Display, Debug)]
 #[display(fmt = "\"{}\" ({} comment)", content, typed)]
 pub struct Comment<'i> {
@@ -23,13 +25,235 @@ pub struct Comment<'i> {
     pub content: &'i str,
 }
 
+/// Side table of the comments in a token stream, which parsers skip, attached to the tokens around them
+///
+/// Nodes look up their comments by their span, as a comment belongs to every node that begins right after it or ends right before it
+#[derive(Clone, Default, Debug)]
+pub struct CommentTable<'i> {
+    leading: BTreeMap<usize, SVec<(Comment<'i>, Span)>>,
+    trailing: BTreeMap<usize, SVec<(Comment<'i>, Span)>>,
+    dangling: SVec<(Comment<'i>, Span)>,
+}
+
 #[derive(Copy, Clone, PartialEq, Display, Debug)]
 pub enum CommentKind {
     /// Lined comments are those that has '//' at the beginning, they finish with a line break
     #[display(fmt = "line")]
     Line,
 
-    /// Block comments are those that are delimited at the beginning by '/*' and by '*/' at the end, so they can be multi-lined
+    /// Block comments are those that are delimited at the beginning by '/*' and by '*/' at the end, so they can be multi-lined and nested
     #[display(fmt = "block")]
     Block,
+
+    /// Outer doc comments document the item that follows them, they begin with '///' or are delimited by '/**' and '*/'
+    #[display(fmt = "outer doc")]
+    OuterDoc,
+
+    /// Inner doc comments document the item that encloses them, they begin with '//!' or are delimited by '/*!' and '*/'
+    #[display(fmt = "inner doc")]
+    InnerDoc,
+}
+
+impl<'i> Comment<'i> {
+    /// Builds a comment from the content that follows '//'
+    pub fn line(content: &'i str) -> Self {
+        if content.starts_with('/') && !content.starts_with("//") {
+            Self::new(CommentKind::OuterDoc, content.get(1..).unwrap())
+        } else if content.starts_with('!') {
+            Self::new(CommentKind::InnerDoc, content.get(1..).unwrap())
+        } else {
+            Self::new(CommentKind::Line, content)
+        }
+    }
+
+    /// Builds a comment from the content between '/*' and '*/'
+    pub fn block(content: &'i str) -> Self {
+        if content.starts_with('*') && !content.starts_with("**") && content.len() > 1 {
+            Self::new(CommentKind::OuterDoc, content.get(1..).unwrap())
+        } else if content.starts_with('!') {
+            Self::new(CommentKind::InnerDoc, content.get(1..).unwrap())
+        } else {
+            Self::new(CommentKind::Block, content)
+        }
+    }
+
+    /// Check if the comment documents an item
+    pub fn is_doc(&self) -> bool {
+        matches!(self.typed, CommentKind::OuterDoc | CommentKind::InnerDoc)
+    }
+}
+
+impl<'i> CommentTable<'i> {
+    /// Comments before the node beginning at the span, like the outer doc comments of an item
+    pub fn leading(&self, span: Span) -> &[(Comment<'i>, Span)] {
+        self.leading
+            .get(span.offset())
+            .map_or(&[], |comments| comments)
+    }
+
+    /// Comments after the node ending at the span which are on the same line as its end
+    pub fn trailing(&self, span: Span) -> &[(Comment<'i>, Span)] {
+        self.trailing
+            .get(&span.range().end)
+            .map_or(&[], |comments| comments)
+    }
+
+    /// Comments after the last token, which do not belong to any node
+    pub fn dangling(&self) -> &[(Comment<'i>, Span)] {
+        &self.dangling
+    }
+
+    /// Doc comments of the node beginning at the span
+    pub fn docs(&self, span: Span) -> impl Iterator<Item = &Comment<'i>> {
+        self.leading(span)
+            .iter()
+            .map(|(comment, _)| comment)
+            .filter(|comment| comment.is_doc())
+    }
+}
+
+impl<'i> TokenStream<'i> {
+    /// Builds the side table of the stream's comments, the source is needed to tell which comments share a line with the token before them
+    ///
+    /// Comments that are not docs and begin on the line where the previous token ends trail that token, the others lead the next token
+    pub fn comments(&self, source: &str) -> CommentTable<'i> {
+        let (mut table, mut pending) = (CommentTable::default(), SVec::new());
+        // End of the previous token along with the end of the last comment trailing it
+        let mut previous: Option<(usize, usize)> = None;
+        for token in self.tokens() {
+            match token.kind() {
+                TokenKind::Comment(comment) => {
+                    let on_line = previous.filter(|(_, last)| {
+                        !comment.is_doc()
+                            && pending.is_empty()
+                            && !source[*last..*token.span().offset()].contains('\n')
+                    });
+                    if let Some((end, _)) = on_line {
+                        table
+                            .trailing
+                            .entry(end)
+                            .or_insert_with(SVec::new)
+                            .push((*comment, *token.span()));
+                        previous = Some((end, token.span().range().end));
+                    } else {
+                        pending.push((*comment, *token.span()));
+                    }
+                }
+                _ => {
+                    if !pending.is_empty() {
+                        table
+                            .leading
+                            .insert(*token.span().offset(), std::mem::take(&mut pending));
+                    }
+                    let end = token.span().range().end;
+                    previous = Some((end, end));
+                }
+            }
+        }
+        table.dangling = pending;
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Comments the source lexes to, along with their spans
+    fn comments(source: &str) -> Vec<(CommentKind, &str, Span)> {
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        stream
+            .tokens()
+            .iter()
+            .filter_map(|token| match token.kind() {
+                TokenKind::Comment(comment) => {
+                    Some((comment.typed, comment.content, *token.span()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kinds_of_comments() {
+        use CommentKind::*;
+        assert_eq!(
+            comments("a /* /* */ */ b"),
+            [(Block, " /* */ ", Span::new(2, 11))]
+        );
+        assert_eq!(comments("/**/"), [(Block, "", Span::new(0, 4))]);
+        assert_eq!(comments("/***/"), [(Block, "*", Span::new(0, 5))]);
+        assert_eq!(comments("/** a */"), [(OuterDoc, " a ", Span::new(0, 8))]);
+        assert_eq!(comments("/*! a */"), [(InnerDoc, " a ", Span::new(0, 8))]);
+        assert_eq!(comments("// a"), [(Line, " a", Span::new(0, 4))]);
+        assert_eq!(comments("/// a"), [(OuterDoc, " a", Span::new(0, 5))]);
+        assert_eq!(comments("////"), [(Line, "//", Span::new(0, 4))]);
+        assert_eq!(comments("//!"), [(InnerDoc, "", Span::new(0, 3))]);
+        assert_eq!(
+            comments("// a\nb // c"),
+            [(Line, " a", Span::new(0, 4)), (Line, " c", Span::new(7, 4))]
+        );
+    }
+
+    /// Contents of the comments
+    fn contents<'i>(comments: &[(Comment<'i>, Span)]) -> Vec<&'i str> {
+        comments
+            .iter()
+            .map(|(comment, _)| comment.content)
+            .collect()
+    }
+
+    /// Span of the token at the index, comments included
+    fn span(stream: &TokenStream, index: usize) -> Span {
+        *stream.tokens()[index].span()
+    }
+
+    #[test]
+    fn docs_lead_the_next_token() {
+        let source = "/// a\n// b\n/// c\nfn f";
+        let (stream, _) = TokenStream::new(source);
+        let table = stream.comments(source);
+        let fn_span = span(&stream, 3);
+        assert_eq!(contents(table.leading(fn_span)), [" a", " b", " c"]);
+        let docs = table.docs(fn_span).map(|comment| comment.content);
+        assert_eq!(docs.collect::<Vec<_>>(), [" a", " c"]);
+        assert!(table.trailing(fn_span).is_empty());
+    }
+
+    #[test]
+    fn comments_on_the_same_line_trail_the_previous_token() {
+        let source = "a; /* b */ // c\n// d\ne /// f\ng";
+        let (stream, _) = TokenStream::new(source);
+        let table = stream.comments(source);
+        assert_eq!(contents(table.trailing(span(&stream, 1))), [" b ", " c"]);
+        assert_eq!(contents(table.leading(span(&stream, 5))), [" d"]);
+        // Doc comments always lead the next token
+        assert!(table.trailing(span(&stream, 5)).is_empty());
+        assert_eq!(contents(table.leading(span(&stream, 7))), [" f"]);
+    }
+
+    #[test]
+    fn comments_after_the_last_token_dangle() {
+        let source = "a // b\n// c\n/* d */";
+        let (stream, _) = TokenStream::new(source);
+        let table = stream.comments(source);
+        assert_eq!(contents(table.trailing(span(&stream, 0))), [" b"]);
+        assert_eq!(contents(table.dangling()), [" c", " d "]);
+        assert!(TokenStream::new("").0.comments("").dangling().is_empty());
+    }
+
+    #[test]
+    fn parser_spans_leave_out_comments_around_tokens() {
+        use chumsky::prelude::*;
+        let (stream, _) = TokenStream::new("/* a */ x /* b */ y /* c */");
+        let parser = any::<_, extra::Default>()
+            .repeated()
+            .collect::<Vec<_>>()
+            .map_with_span(|tokens, span: Span| (tokens.len(), span));
+        assert_eq!(
+            parser.parse(stream).into_result(),
+            Ok((2, Span::new(8, 11)))
+        );
+    }
 }
@@ -0,0 +1,53 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::*;
+
+/// Format string literal like `f"hello {name}"`, which interpolates expressions into its text
+#[derive(Clone, PartialEq, Debug)]
+pub struct FormatStr<'i>(pub Vec<FormatPart<'i>>);
+
+/// Parts of a format string literal
+#[derive(Clone, PartialEq, Debug)]
+pub enum FormatPart<'i> {
+    /// Text with its escape sequences and doubled braces decoded
+    Text(Symbol, Span),
+
+    /// Tokens of an interpolated expression along with its format spec, which follows a colon
+    Expr(TokenStream<'i>, Option<Symbol>, Span),
+}
+
+impl Display for FormatStr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "f\"")?;
+        for part in self.0.iter() {
+            match part {
+                FormatPart::Text(text, _) => {
+                    write!(f, "{}", text.as_str().replace('{', "{{").replace('}', "}}"))?
+                }
+                FormatPart::Expr(_, Some(spec), _) => write!(f, "{{..:{}}}", spec)?,
+                FormatPart::Expr(..) => write!(f, "{{..}}")?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+impl TokenOrigin for FormatStr<'_> {
+    fn origin(&self) -> Option<Symbol> {
+        Some(Symbol::intern(&self.to_string()))
+    }
+}
@@ -22,210 +22,200 @@ pub trait Lexeme<'i> {
 }
 
 /// Input type for the lexer that keeps track of the string's offset relative to the source
-#[derive(Copy, Clone, Constructor, Default, Debug)]
+#[derive(Copy, Clone, Constructor, Getters, Default, Debug)]
+#[get = "pub"]
 pub struct Fragment<'i> {
     offset: usize,
     ptr: &'i str,
 }
 
-/// Describes the state of the input fragmentation process by keeping track of the kind of character it encounters
-#[derive(Clone, Default, Debug)]
-struct DefragTracker {
-    inner: [char; 2],
-    lit_char: bool,
-    lit_string: bool,
-    line_comment: bool,
-    block_comment: bool,
-}
-
-/// Decisition of the defragment tracker
+/// Outcome of lexing a token that may span multiple lines
 #[derive(Clone, Debug)]
-enum DefragResult {
-    Digest,
-    Push,
-    LineComment,
-    BlockComment,
-    Ch,
-    Str,
+enum Enclosed<'i> {
+    Closed(Token<'i>),
+    /// The token reached the end of the fragment, holds its description and the length of its opening delimiter
+    Open(&'static str, usize),
 }
 
+/// Inputs smaller than this are lexed in a single thread even when the `parallel` feature is enabled
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 1 << 18;
+
 impl<'i> TokenStream<'i> {
-    pub fn new(input: &'i str) -> Self {
-        TokenStream::defragment(input)
-    }
-
-    fn defragment(i: &'i str) -> Self {
-        let (mut pool, token_buffer, mut fragment, mut ctrl_flags): (
-            _,
-            SegQueue<Token<'i>>,
-            Option<Fragment<'i>>,
-            DefragTracker,
-        ) = (
-            Pool::new(std::thread::available_parallelism().unwrap().get() as u32),
-            SegQueue::new(),
-            None,
-            DefragTracker::default(),
-        );
-        pool.scoped(|scope| {
-            let mut chars: std::iter::Enumerate<std::str::Chars> = i.chars().enumerate();
-            while chars.clone().count() != 0 || fragment.is_some() {
-                let (ch_offset, ch) = {
-                    if let Some(chars) = chars.next() {
-                        chars
-                    } else {
-                        if let Some(value) = fragment {
-                            let token_buffer = &token_buffer;
-                            scope.execute(move || {
-                                value.digest().iter().for_each(|s| token_buffer.push(*s));
-                            });
-                        }
-                        break;
-                    }
-                };
-                let _can_defragment = ctrl_flags.can_defragment(ch);
-                match _can_defragment {
-                    DefragResult::Push => {
-                        if let Some(value) = fragment.as_mut() {
-                            value.ptr = i
-                                .get(value.offset..value.offset + value.ptr.len() + 1)
-                                .unwrap();
-                        } else {
-                            fragment.replace(Fragment::<'i>::new(
-                                ch_offset,
-                                i.get(ch_offset..ch_offset + 1).unwrap(),
-                            ));
-                        }
-                    }
-                    DefragResult::Digest => {
-                        if let Some(value) = fragment.take() {
-                            let token_buffer = &token_buffer;
-                            scope.execute(move || {
-                                value.digest().iter().for_each(|s| token_buffer.push(*s));
-                            });
-                        }
-                    }
-                    _ => {
-                        if let Some(mut value) = fragment.take() {
-                            value.ptr = i
-                                .get(value.offset.clone()..value.offset + value.ptr.len() + 1)
-                                .unwrap();
-                            token_buffer.push(Token::new(
-                                {
-                                    match _can_defragment {
-                                        DefragResult::LineComment => {
-                                            TokenKind::Comment(Comment::new(
-                                                CommentKind::Line,
-                                                value.ptr.get(2..).unwrap(),
-                                            ))
-                                        }
-                                        DefragResult::BlockComment => {
-                                            TokenKind::Comment(Comment::new(
-                                                CommentKind::Block,
-                                                value.ptr.get(2..value.ptr.len() - 2).unwrap(),
-                                            ))
-                                        }
-                                        DefragResult::Ch => TokenKind::Literal(Literal::Char(
-                                            value.ptr.get(1..2).unwrap(),
-                                        )),
-                                        DefragResult::Str => TokenKind::Literal(Literal::Str(
-                                            value.ptr.get(1..value.ptr.len() - 1).unwrap(),
-                                        )),
-                                        _ => self::panic!(),
-                                    }
-                                },
-                                Span::new(value.offset, value.ptr.len()),
-                            ));
-                        } else {
-                            self::panic!(
-                                "Expected a possible token but there is no fragment in the buffer!"
-                            )
-                        }
-                    }
+    /// Lexes the input into a token stream along with every lexical error found in it
+    pub fn new(input: &'i str) -> (Self, Vec<Error>) {
+        let (tokens, errors) = TokenStream::defragment(input);
+        (Self::from_tokens(tokens, None, None), errors)
+    }
+
+    /// Lexes the input like [`TokenStream::new`] but keeps the text between tokens as their trivia, so the source can be rebuilt with [`TokenStream::to_source`]
+    ///
+    /// Text skipped because of lexical errors is kept as trivia as well
+    pub fn lossless(input: &'i str) -> (Self, Vec<Error>) {
+        let (mut tokens, errors) = TokenStream::defragment(input);
+        let lossless = Lossless::attach(&mut tokens, input);
+        (Self::from_tokens(tokens, None, Some(lossless)), errors)
+    }
+
+    /// Rebuilds the source byte for byte from the tokens and their trivia, streams that are not lossless cannot be rebuilt
+    pub fn to_source(&self) -> Option<String> {
+        let lossless = self.lossless?;
+        let mut source = String::new();
+        for token in self.tokens() {
+            source.push_str(token.trivia.leading);
+            source.push_str(&lossless.source[token.span.range()]);
+            source.push_str(token.trivia.trailing);
+        }
+        source.push_str(lossless.eof);
+        Some(source)
+    }
+
+    fn defragment(i: &'i str) -> (SVec<Token<'i>>, Vec<Error>) {
+        #[cfg(feature = "parallel")]
+        if i.len() >= PARALLEL_THRESHOLD {
+            let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+            return TokenStream::defragment_parallel(i, threads);
+        }
+        let (tokens, errors, _) = Fragment::start(i).digest(true);
+        (tokens, errors)
+    }
+
+    /// Lexes chunks of the input in parallel, they are split after line breaks as only block comments and strings cross them
+    ///
+    /// When a chunk leaves one of those open, the next chunk is lexed again from where the token began,
+    /// replacing the errors the previous chunk reported within it
+    #[cfg(any(test, feature = "parallel"))]
+    fn defragment_parallel(i: &'i str, threads: usize) -> (SVec<Token<'i>>, Vec<Error>) {
+        let mut bounds = vec![*Fragment::start(i).offset()];
+        for thread in 1..threads {
+            let target = (i.len() * thread / threads).max(*bounds.last().unwrap());
+            if let Some(line_break) = i.as_bytes()[target..].iter().position(|ch| *ch == b'\n') {
+                if target + line_break + 1 < i.len() {
+                    bounds.push(target + line_break + 1);
                 }
             }
-        });
-        Self(
-            token_buffer
-                .into_iter()
-                .sorted_unstable_by(|lhs, rhs| match (lhs.span.offset(), rhs.span.offset()) {
-                    (lhs, rhs) if lhs > rhs => std::cmp::Ordering::Greater,
-                    (lhs, rhs) if lhs == rhs => std::cmp::Ordering::Equal,
-                    (lhs, rhs) if lhs < rhs => std::cmp::Ordering::Less,
-                    (_, _) => self::panic!(),
+        }
+        bounds.push(i.len());
+        bounds.dedup();
+        let chunks = bounds
+            .windows(2)
+            .map(|bound| bound[0]..bound[1])
+            .collect::<Vec<_>>();
+        let digested = std::thread::scope(|scope| {
+            chunks
+                .iter()
+                .map(|chunk| {
+                    let (chunk, eof) = (chunk.clone(), chunk.end == i.len());
+                    scope.spawn(move || Fragment::new(chunk.start, &i[chunk]).digest(eof))
                 })
-                .collect::<SVec<Token<'i>>>(),
-            None,
-        )
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        let (mut tokens, mut errors, mut open) = (SVec::new(), Vec::new(), None);
+        for (chunk, digested) in chunks.into_iter().zip(digested) {
+            let (chunk_tokens, chunk_errors, chunk_open) = match open {
+                Some(start) => {
+                    errors.retain(|error: &Error| {
+                        error.span().is_none_or(|span| *span.offset() < start)
+                    });
+                    Fragment::new(start, &i[start..chunk.end]).digest(chunk.end == i.len())
+                }
+                None => digested,
+            };
+            tokens.extend(chunk_tokens);
+            errors.extend(chunk_errors);
+            open = chunk_open;
+        }
+        (tokens, errors)
     }
 }
 
-impl DefragTracker {
-    pub fn can_defragment(&mut self, ch: char) -> DefragResult {
-        self.set_char(ch);
-        match &self.inner {
-            ['/', '/'] => self.line_comment = true,
-            ['/', '*'] => self.block_comment = true,
-            ['*', '/'] => {
-                self.block_comment = false;
-                return DefragResult::BlockComment;
-            }
-            [_, '\''] => {
-                if !self.line_comment {
-                    if self.lit_char {
-                        self.lit_char = false;
-                        return DefragResult::Ch;
-                    } else if !self.lit_string {
-                        self.lit_char = true;
-                    }
-                }
-            }
-            [_, '\"'] => {
-                if !self.line_comment {
-                    if self.lit_string {
-                        self.lit_string = false;
-                        return DefragResult::Str;
-                    } else if !self.lit_char {
-                        self.lit_string = true;
-                    }
-                }
-            }
-            _ if ch == '\n' => {
-                if !self.lit_char && !self.lit_string && !self.block_comment {
-                    if self.line_comment {
-                        self.line_comment = false;
-                        return DefragResult::LineComment;
-                    } else {
-                        return DefragResult::Digest;
-                    }
-                }
-            }
-            _ if ch.is_whitespace() => {
-                if !self.line_comment && !self.block_comment && !self.lit_char && !self.lit_string {
-                    return DefragResult::Digest;
-                }
-            }
-            _ => (),
+impl<'i> Lossless<'i> {
+    /// Attaches the text between tokens to them as trivia
+    pub(crate) fn attach(tokens: &mut [Token<'i>], input: &'i str) -> Self {
+        let mut end = 0;
+        for index in 0..tokens.len() {
+            let start = *tokens[index].span.offset();
+            let next = tokens
+                .get(index + 1)
+                .map_or(input.len(), |next| *next.span.offset());
+            let token_end = start + tokens[index].span.length();
+            let gap = &input[token_end..next];
+            let trailing = gap
+                .find(|ch: char| ch == '\n' || !ch.is_whitespace())
+                .map_or(gap.len(), |length| {
+                    length + gap[length..].starts_with('\n') as usize
+                });
+            tokens[index].trivia = Trivia {
+                leading: &input[end..start],
+                trailing: &gap[..trailing],
+            };
+            end = token_end + trailing;
+        }
+        Self {
+            source: input,
+            eof: &input[end..],
         }
-        DefragResult::Push
     }
 
-    pub fn set_char(&mut self, ch: char) {
-        self.inner.rotate_left(1);
-        self.inner[1] = ch;
+    /// Only slices reaching the end of the stream keep the trivia after the last token
+    pub(crate) fn slice(self, to_end: bool) -> Self {
+        Self {
+            eof: if to_end { self.eof } else { "" },
+            ..self
+        }
     }
 }
 
 impl<'i> Fragment<'i> {
+    /// Builds the fragment of the whole input, which skips a leading byte order mark and shebang line so offsets stay relative to the input
+    ///
+    /// A shebang is a first line beginning with `#!` which is not followed by `[`, as that is an inner attribute
+    pub fn start(input: &'i str) -> Self {
+        let mut fragment = Fragment::new(0, input);
+        if input.starts_with('\u{feff}') {
+            fragment.take('\u{feff}'.len_utf8());
+        }
+        if let Some(rest) = fragment.ptr.strip_prefix("#!") {
+            if !rest.trim_start().starts_with('[') {
+                fragment.take(fragment.ptr.find('\n').unwrap_or(fragment.ptr.len()));
+            }
+        }
+        fragment
+    }
+
     /// Check if can be consumed from the fragment
     pub fn can_consume(&self) -> bool {
         !self.ptr.is_empty()
     }
 
-    /// Consumes input
+    /// Consumes the given amount of bytes from the input
     fn take(&mut self, n: usize) {
         assert!(n != 0, "Cannot take 0 characters from input!");
         self.offset += n;
-        self.ptr = self.ptr.get(n..).unwrap().into();
+        self.ptr = self.ptr.get(n..).unwrap();
+    }
+
+    /// Consumes the given amount of bytes as a token
+    fn take_token(&mut self, n: usize, kind: TokenKind<'i>) -> Token<'i> {
+        let token = Token::new(kind, Span::new(self.offset, n));
+        self.take(n);
+        token
+    }
+
+    /// Skips whitespace and checks if there is anything left to consume
+    pub fn skip_whitespace(&mut self) -> bool {
+        let length = self
+            .ptr
+            .char_indices()
+            .find(|(_, ch)| !ch.is_whitespace())
+            .map_or(self.ptr.len(), |(length, _)| length);
+        if length != 0 {
+            self.take(length);
+        }
+        self.can_consume()
     }
 
     /// Consumes a specific character sequence
@@ -239,45 +229,85 @@ impl<'i> Fragment<'i> {
         None
     }
 
-    /// Try to consume a number
-    pub fn consume_number(&mut self) -> Option<((bool, &'i str), Span)> {
-        let (mut is_decimal, mut length) = (bool::default(), 0_usize);
-        for ch in self.ptr.chars() {
-            if ch.is_numeric() {
-                length += 1;
-            } else if ch == '.' {
-                if is_decimal {
-                    return None;
-                }
-                is_decimal = true;
-                length += 1;
-            } else {
-                break;
-            }
-        }
-        if length != 0 {
-            let number_span = Span::new(self.offset, length);
-            let res = Some(((is_decimal, self.ptr.get(..length).unwrap()), number_span));
-            self.take(length);
-            res
-        } else {
-            None
+    /// Consumes a specific character sequence only if it is not followed by an identifier character
+    pub fn consume_word(&mut self, txt: &str) -> Option<Span> {
+        if !self.ptr.starts_with(txt)
+            || self
+                .ptr
+                .get(txt.len()..)
+                .and_then(|rest| rest.chars().next())
+                .is_some_and(|ch| ch.is_xid_continue())
+        {
+            return None;
         }
+        self.consume_pattern(txt)
     }
 
-    /// Try to consume an identifier
-    pub fn consume_id(&mut self) -> Option<(&'i str, Span)> {
-        let first_char = self.ptr.chars().next().unwrap();
-        if !first_char.is_digit(10) && !first_char.is_whitespace() {
-            let mut length = 0_usize;
-            for ch in self.ptr.chars() {
-                if ch.is_alphanumeric() || ch == '_' {
-                    length += 1;
-                } else {
-                    break;
+    /// Try to consume a number split from its type suffix, which holds the radix prefix, digit separators, fraction and exponent
+    ///
+    /// A dot only belongs to the number when a digit follows so ranges like `0..9` and method calls are kept apart
+    pub fn consume_number(&mut self) -> Option<((&'i str, &'i str), Span)> {
+        let bytes = self.ptr.as_bytes();
+        if !bytes.first()?.is_ascii_digit() {
+            return None;
+        }
+        let digits = |from: usize, hex: bool| {
+            bytes[from..]
+                .iter()
+                .take_while(|ch| {
+                    **ch == b'_'
+                        || if hex {
+                            ch.is_ascii_hexdigit()
+                        } else {
+                            ch.is_ascii_digit()
+                        }
+                })
+                .count()
+        };
+        let mut length = if bytes[0] == b'0' && matches!(bytes.get(1), Some(b'x' | b'o' | b'b')) {
+            2 + digits(2, true)
+        } else {
+            let mut length = digits(0, false);
+            if bytes.get(length) == Some(&b'.')
+                && bytes.get(length + 1).is_some_and(u8::is_ascii_digit)
+            {
+                length += 1 + digits(length + 1, false);
+            }
+            if matches!(bytes.get(length), Some(b'e' | b'E')) {
+                let sign = matches!(bytes.get(length + 1), Some(b'+' | b'-')) as usize;
+                if bytes.get(length + 1 + sign).is_some_and(u8::is_ascii_digit) {
+                    length += 1 + sign + digits(length + 1 + sign, false);
                 }
             }
-            if length != 0 {
+            length
+        };
+        let number = length;
+        length += bytes[length..]
+            .iter()
+            .take_while(|ch| ch.is_ascii_alphanumeric() || **ch == b'_')
+            .count();
+        let res = (
+            (
+                self.ptr.get(..number).unwrap(),
+                self.ptr.get(number..length).unwrap(),
+            ),
+            Span::new(self.offset, length),
+        );
+        self.take(length);
+        Some(res)
+    }
+
+    /// Try to consume an identifier following UAX #31, which begins with a `XID_Start` character or an underscore and goes on with `XID_Continue` characters
+    pub fn consume_id(&mut self) -> Option<(&'i str, Span)> {
+        let first_char = self.ptr.chars().next()?;
+        if first_char.is_xid_start() || first_char == '_' {
+            let length = self
+                .ptr
+                .char_indices()
+                .find(|(_, ch)| !ch.is_xid_continue())
+                .map_or(self.ptr.len(), |(length, _)| length);
+            // A lone underscore is punctuation
+            if length != 1 || first_char != '_' {
                 let res = (
                     self.ptr.get(..length).unwrap(),
                     Span::new(self.offset, length),
@@ -289,41 +319,665 @@ impl<'i> Fragment<'i> {
         None
     }
 
+    /// Try to consume a line comment, which ends before the line break
+    pub fn consume_line_comment(&mut self) -> Option<Token<'i>> {
+        if !self.ptr.starts_with("//") {
+            return None;
+        }
+        let length = self.ptr.find('\n').unwrap_or(self.ptr.len());
+        let content = self.ptr.get(2..length).unwrap();
+        Some(self.take_token(length, TokenKind::Comment(Comment::line(content))))
+    }
+
+    /// Try to consume a block comment, which can be nested
+    fn consume_block_comment(&mut self) -> Option<Enclosed<'i>> {
+        if !self.ptr.starts_with("/*") {
+            return None;
+        }
+        let (bytes, mut depth, mut length) = (self.ptr.as_bytes(), 1_usize, 2_usize);
+        while length < bytes.len() {
+            match &bytes[length..] {
+                [b'/', b'*', ..] => (depth, length) = (depth + 1, length + 2),
+                [b'*', b'/', ..] => {
+                    (depth, length) = (depth - 1, length + 2);
+                    if depth == 0 {
+                        let content = self.ptr.get(2..length - 2).unwrap();
+                        return Some(Enclosed::Closed(
+                            self.take_token(length, TokenKind::Comment(Comment::block(content))),
+                        ));
+                    }
+                }
+                _ => length += 1,
+            }
+        }
+        self.take(self.ptr.len());
+        Some(Enclosed::Open("block comment", 2))
+    }
+
+    /// Try to consume a raw string literal, which is delimited by `r"` and `"` with any amount of hashes after `r` and after the closing quote
+    fn consume_raw_str(&mut self) -> Option<Enclosed<'i>> {
+        let hashes = self
+            .ptr
+            .strip_prefix('r')?
+            .bytes()
+            .take_while(|ch| *ch == b'#')
+            .count();
+        if self.ptr.as_bytes().get(hashes + 1) != Some(&b'"') {
+            return None;
+        }
+        let closing = format!("\"{}", "#".repeat(hashes));
+        if let Some(end) = self.ptr[hashes + 2..].find(closing.as_str()) {
+            let content = self.ptr.get(hashes + 2..hashes + 2 + end).unwrap();
+            Some(Enclosed::Closed(self.take_token(
+                hashes + 2 + end + closing.len(),
+                TokenKind::Literal(Literal::Str(Symbol::intern(content))),
+            )))
+        } else {
+            self.take(self.ptr.len());
+            Some(Enclosed::Open("raw string literal", hashes + 2))
+        }
+    }
+
+    /// Try to consume a string literal, decoding its escape sequences
+    fn consume_str(&mut self, errors: &mut Vec<Error>) -> Option<Enclosed<'i>> {
+        if !self.ptr.starts_with('"') {
+            return None;
+        }
+        let mut chars = self.ptr.char_indices().skip(1);
+        while let Some((length, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => {
+                    let literal =
+                        Literal::str(self.ptr.get(1..length).unwrap(), self.offset + 1, errors);
+                    return Some(Enclosed::Closed(
+                        self.take_token(length + 1, TokenKind::Literal(literal)),
+                    ));
+                }
+                _ => (),
+            }
+        }
+        self.take(self.ptr.len());
+        Some(Enclosed::Open("string literal", 1))
+    }
+
+    /// Try to consume a format string literal, which is delimited by `f"` and `"` and interpolates the expressions enclosed by braces
+    ///
+    /// Expressions are lexed into their own token streams and may be followed by a format spec after a colon, braces are written by doubling them
+    fn consume_format_str(&mut self, errors: &mut Vec<Error>) -> Option<Enclosed<'i>> {
+        if !self.ptr.starts_with("f\"") {
+            return None;
+        }
+        let bytes = self.ptr.as_bytes();
+        let (mut parts, mut text) = (Vec::new(), CompactString::default());
+        // Beginning of the text part and of the text that is not decoded yet
+        let (mut length, mut part, mut run) = (2, 2, 2);
+        while length < bytes.len() {
+            let decode = |text: &mut CompactString, errors: &mut Vec<Error>, end: usize| {
+                text.push_str(&Literal::unescape(
+                    self.ptr.get(run..end).unwrap(),
+                    self.offset + run,
+                    errors,
+                ))
+            };
+            match (bytes[length], bytes.get(length + 1)) {
+                (b'\\', _) => length += 2,
+                (b'{', Some(b'{')) | (b'}', Some(b'}')) => {
+                    decode(&mut text, errors, length);
+                    text.push(bytes[length] as char);
+                    (length, run) = (length + 2, length + 2);
+                }
+                (b'}', _) => {
+                    decode(&mut text, errors, length);
+                    errors.push(Error::Literal {
+                        found: CompactString::from("}"),
+                        help: Some(CompactString::from(
+                            "Braces that do not enclose an expression are written as `{{` and `}}`",
+                        )),
+                        span: Some(Span::new(self.offset + length, 1)),
+                    });
+                    (length, run) = (length + 1, length + 1);
+                }
+                (b'{', _) | (b'"', _) => {
+                    decode(&mut text, errors, length);
+                    if !text.is_empty() {
+                        parts.push(FormatPart::Text(
+                            Symbol::intern(&text),
+                            Span::new(self.offset + part, length - part),
+                        ));
+                        text.clear();
+                    }
+                    if bytes[length] == b'"' {
+                        let format = TokenKind::Format(FormatStr(parts));
+                        return Some(Enclosed::Closed(self.take_token(length + 1, format)));
+                    }
+                    let (spec, close) = match Self::find_interpolation_end(bytes, length + 1) {
+                        Ok(end) => end,
+                        Err(Some(quote)) => {
+                            errors.push(Error::Literal {
+                                found: CompactString::from("\""),
+                                help: Some(CompactString::from(
+                                    "The interpolated expression is not closed by `}` before the format string literal ends",
+                                )),
+                                span: Some(Span::new(self.offset + quote, 1)),
+                            });
+                            (length, part, run) = (quote, quote, quote);
+                            continue;
+                        }
+                        Err(None) => break,
+                    };
+                    let expr = Fragment::new(
+                        self.offset + length + 1,
+                        self.ptr.get(length + 1..spec.unwrap_or(close)).unwrap(),
+                    );
+                    let (tokens, expr_errors, _) = expr.digest(true);
+                    errors.extend(expr_errors);
+                    if tokens.is_empty() {
+                        errors.push(Error::Literal {
+                            found: CompactString::from(self.ptr.get(length..close + 1).unwrap()),
+                            help: Some(CompactString::from(
+                                "Braces enclose the expression to interpolate, they are written as `{{` and `}}`",
+                            )),
+                            span: Some(Span::new(self.offset + length, close + 1 - length)),
+                        });
+                    }
+                    parts.push(FormatPart::Expr(
+                        TokenStream::from_tokens(tokens, None, None),
+                        spec.map(|spec| Symbol::intern(self.ptr.get(spec + 1..close).unwrap())),
+                        Span::new(self.offset + length, close + 1 - length),
+                    ));
+                    (length, part, run) = (close + 1, close + 1, close + 1);
+                }
+                _ => length += 1,
+            }
+        }
+        self.take(self.ptr.len());
+        Some(Enclosed::Open("format string literal", 2))
+    }
+
+    /// Finds the colon that begins the format spec and the brace that closes an interpolated expression
+    ///
+    /// Nested delimiters and string and char literals within the expression are skipped, so they may hold colons and braces.
+    /// A string literal left open at a line break is the quote closing the format string literal, which is given back
+    fn find_interpolation_end(
+        bytes: &[u8],
+        from: usize,
+    ) -> Result<(Option<usize>, usize), Option<usize>> {
+        let (mut index, mut depth, mut spec) = (from, 0_usize, None);
+        while index < bytes.len() {
+            match (bytes[index], bytes.get(index + 1)) {
+                (b'}', _) if depth == 0 || spec.is_some() => return Ok((spec, index)),
+                _ if spec.is_some() => (),
+                (b'"', _) => {
+                    let quote = index;
+                    index += 1;
+                    while bytes.get(index) != Some(&b'"') {
+                        match bytes.get(index) {
+                            Some(b'\\') => index += 2,
+                            Some(b'\n') | None => return Err(Some(quote)),
+                            Some(_) => index += 1,
+                        }
+                    }
+                }
+                (b'\'', _) => index = Self::char_literal_end(bytes, index).unwrap_or(index),
+                (b'(' | b'[' | b'{', _) => depth += 1,
+                (b')' | b']' | b'}', _) => depth = depth.saturating_sub(1),
+                (b':', Some(b':')) => index += 1,
+                (b':', _) if depth == 0 => spec = Some(index),
+                _ => (),
+            }
+            index += 1;
+        }
+        Err(None)
+    }
+
+    /// Finds the apostrophe closing the char literal opened at the index, there is none for lifetimes
+    fn char_literal_end(bytes: &[u8], from: usize) -> Option<usize> {
+        let width = match *bytes.get(from + 1)? {
+            b'\\' => {
+                let mut index = from + 2;
+                loop {
+                    match bytes.get(index)? {
+                        b'\'' => return Some(index),
+                        b'\\' => index += 2,
+                        b'\n' => return None,
+                        _ => index += 1,
+                    }
+                }
+            }
+            b'\'' | b'\n' => return None,
+            byte if byte < 0x80 => 1,
+            byte if byte < 0xE0 => 2,
+            byte if byte < 0xF0 => 3,
+            _ => 4,
+        };
+        (bytes.get(from + 1 + width) == Some(&b'\'')).then_some(from + 1 + width)
+    }
+
+    /// Try to consume a lifetime, which is an apostrophe followed by an identifier that is not closed by another apostrophe like char literals are
+    pub fn consume_lifetime(&mut self) -> Option<(&'i str, Span)> {
+        let mut name = Fragment::new(self.offset + 1, self.ptr.strip_prefix('\'')?);
+        let ident = if let Some((ident, _)) = name.consume_id() {
+            ident
+        } else {
+            name.consume_word("_").map(|_| "_")?
+        };
+        if name.ptr.starts_with('\'') {
+            return None;
+        }
+        let res = (ident, Span::new(self.offset, ident.len() + 1));
+        self.take(ident.len() + 1);
+        Some(res)
+    }
+
+    /// Try to consume a char literal, which cannot cross a line break
+    pub fn consume_char(&mut self, errors: &mut Vec<Error>) -> Option<Option<Token<'i>>> {
+        if !self.ptr.starts_with('\'') {
+            return None;
+        }
+        let mut chars = self.ptr.char_indices().skip(1);
+        while let Some((length, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    chars.next();
+                }
+                '\'' => {
+                    let literal =
+                        Literal::char(self.ptr.get(1..length).unwrap(), self.offset + 1, errors);
+                    return Some(Some(
+                        self.take_token(length + 1, TokenKind::Literal(literal)),
+                    ));
+                }
+                '\n' => break,
+                _ => (),
+            }
+        }
+        errors.push(Error::Unterminated {
+            found: CompactString::from("char literal"),
+            span: Some(Span::new(self.offset, 1)),
+        });
+        self.take(1);
+        Some(None)
+    }
+
     /// Consumes any character once
     pub fn consume_any_once(&mut self) -> (char, Span) {
         let (offset, any_char) = (self.offset, self.ptr.chars().next().unwrap());
-        self.take(1);
-        (any_char, Span::new(offset, 1))
-    }
-
-    /// Converts fragment to token stream
-    pub fn digest(&self) -> SVec<Token<'i>> {
-        let mut fragment = self.clone();
-        let mut buffer: SVec<Token> = SVec::new();
-        while fragment.can_consume() {
-            if let Some(kw) = Keyword::parse(&mut fragment) {
-                buffer.push(kw);
-                continue;
-            } else if let Some(punc) = Punctuation::parse(&mut fragment) {
-                buffer.push(punc);
-                continue;
-            } else if let Some(lit) = Literal::parse(&mut fragment) {
-                buffer.push(lit);
-                continue;
-            } else if let Some((id, span)) = fragment.consume_id() {
-                buffer.push(Token::new(TokenKind::Identifier(id), span));
-            } else {
-                let (any_char, span) = fragment.consume_any_once();
-                buffer.push(Token::new(TokenKind::Unknown(any_char), span));
-                continue;
+        self.take(any_char.len_utf8());
+        (any_char, Span::new(offset, any_char.len_utf8()))
+    }
+
+    /// Converts fragment to token stream along with the errors found in it
+    ///
+    /// Also returns where the token that is still open at the end of the fragment began, which is reported as unterminated at the end of the input
+    pub fn digest(&self, eof: bool) -> (SVec<Token<'i>>, Vec<Error>, Option<usize>) {
+        let mut fragment = *self;
+        let (mut buffer, mut errors, mut open): (SVec<Token>, Vec<Error>, Option<usize>) =
+            (SVec::new(), Vec::new(), None);
+        while fragment.skip_whitespace() {
+            if let Some(token) = fragment.step(eof, &mut errors, &mut open) {
+                buffer.push(token);
+            }
+        }
+        (buffer, errors, open)
+    }
+
+    /// Consumes the next token, which is missing when only an error or a token that is still open was found
+    pub(crate) fn step(
+        &mut self,
+        eof: bool,
+        errors: &mut Vec<Error>,
+        open: &mut Option<usize>,
+    ) -> Option<Token<'i>> {
+        let start = self.offset;
+        let enclosed = if let Some(comment) = self.consume_block_comment() {
+            comment
+        } else if let Some(raw) = self.consume_raw_str() {
+            raw
+        } else if let Some(format) = self.consume_format_str(errors) {
+            format
+        } else if let Some(str) = self.consume_str(errors) {
+            str
+        } else {
+            return self.consume_token(errors);
+        };
+        match enclosed {
+            Enclosed::Closed(token) => Some(token),
+            Enclosed::Open(found, opening) => {
+                if eof {
+                    errors.push(Error::Unterminated {
+                        found: CompactString::from(found),
+                        span: Some(Span::new(start, opening)),
+                    });
+                }
+                *open = Some(start);
+                None
             }
         }
-        buffer
+    }
+
+    /// Consumes a token that cannot span multiple lines, characters that do not begin any token are reported
+    fn consume_token(&mut self, errors: &mut Vec<Error>) -> Option<Token<'i>> {
+        if let Some(comment) = self.consume_line_comment() {
+            Some(comment)
+        } else if let Some((name, span)) = self.consume_lifetime() {
+            Some(Token::new(TokenKind::lifetime(name), span))
+        } else if let Some(ch) = self.consume_char(errors) {
+            ch
+        } else if let Some(kw) = Keyword::parse(self) {
+            Some(kw)
+        } else if let Some(lit) = Literal::parse(self) {
+            Some(lit)
+        } else if let Some(number) = Literal::number(self, errors) {
+            Some(number)
+        } else if let Some((id, span)) = self.consume_id() {
+            Some(Token::new(TokenKind::identifier(id), span))
+        } else if let Some(punc) = Punctuation::parse(self) {
+            Some(punc)
+        } else {
+            let (any_char, span) = self.consume_any_once();
+            errors.push(Error::Unknown {
+                found: any_char,
+                span: Some(span),
+            });
+            None
+        }
     }
 }
 
-impl<'i> From<&'i str> for TokenStream<'i> {
-    fn from(value: &'i str) -> Self {
-        TokenStream::defragment(value)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Source whose block comments, strings and errors span several lines so chunks split inside them
+    fn source() -> String {
+        let mut source = String::from("\u{feff}#!/usr/bin/env kiban run\n");
+        for i in 0..64 {
+            source.push_str(&format!(
+                "fn f{i}() {{ let s = \"first\n  second {i}\n\"; /* open\n /* nested\n */ still */ s }}\n\
+                 const R{i}: &str = r#\"raw \"\n quoted\"#; // line {i}\n\
+                 let c = '\\q'; let x = 0x{i:x}_u8 .. 1.5e3; $\n\
+                 let f = f\"a\\q {{x}}\n b\";\n"
+            ));
+        }
+        source.push_str("/* never closed\n\n");
+        source
+    }
+
+    #[test]
+    fn parallel_chunks_match_single_pass() {
+        let source = source();
+        let (tokens, errors, _) = Fragment::start(&source).digest(true);
+        assert!(errors.len() >= 64 * 2, "{errors:?}");
+        for threads in 1..=32 {
+            let (parallel, parallel_errors) = TokenStream::defragment_parallel(&source, threads);
+            assert_eq!(
+                format!("{parallel:?}"),
+                format!("{tokens:?}"),
+                "{threads} chunks"
+            );
+            assert_eq!(parallel_errors, errors, "{threads} chunks");
+        }
+    }
+
+    /// Kinds of the lexed tokens along with their offsets
+    fn lex(source: &str) -> Vec<(TokenKind<'_>, usize)> {
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        stream
+            .tokens()
+            .iter()
+            .map(|token| (token.kind().clone(), *token.span().offset()))
+            .collect()
+    }
+
+    #[test]
+    fn byte_order_mark_and_shebang_are_skipped() {
+        let source = "\u{feff}#!/usr/bin/env kiban run\nfn main";
+        let start = source.find("fn").unwrap();
+        assert_eq!(
+            lex(source),
+            [
+                (FN, start),
+                (TokenKind::Identifier(Symbol::intern("main")), start + 3)
+            ]
+        );
+        assert_eq!(lex("\u{feff}fn"), [(FN, 3)]);
+        assert_eq!(lex("#!/usr/bin/env kiban run"), []);
+        assert_eq!(lex("#!\nfn"), [(FN, 3)]);
+    }
+
+    #[test]
+    fn inner_attribute_is_not_a_shebang() {
+        for (source, hash, bracket) in [("#![attr]\nfn", 0, 2), ("\u{feff}#!  [attr]", 3, 7)] {
+            let tokens = lex(source);
+            assert_eq!(
+                tokens[..3],
+                [(HASH, hash), (EXCLM, hash + 1), (OP_SQ_BRACKET, bracket)]
+            );
+        }
+    }
+
+    #[test]
+    fn shebang_only_on_the_first_line() {
+        assert_eq!(
+            lex("fn\n#!x"),
+            [
+                (FN, 0),
+                (HASH, 3),
+                (EXCLM, 4),
+                (TokenKind::Identifier(Symbol::intern("x")), 5)
+            ]
+        );
+    }
+
+    /// Interpolated expressions of a format string literal, as the kinds of their tokens along with their spans
+    fn interpolations(source: &str) -> Vec<(Vec<TokenKind<'_>>, Span)> {
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        let Some(TokenKind::Format(FormatStr(parts))) = stream.tokens().first().map(Token::kind)
+        else {
+            panic!("{source} is not a format string literal");
+        };
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                FormatPart::Expr(tokens, _, span) => Some((
+                    tokens
+                        .tokens()
+                        .iter()
+                        .map(|token| token.kind().clone())
+                        .collect(),
+                    *span,
+                )),
+                FormatPart::Text(..) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interpolations_skip_char_literals() {
+        let (c, a) = (Symbol::intern("c"), Symbol::intern("a"));
+        for (source, quoted) in [("f\"{c == '}'}\"", '}'), ("f\"{a == ':'}\"", ':')] {
+            let ident = if quoted == '}' { c } else { a };
+            let literal = TokenKind::Literal(Literal::Char(quoted));
+            assert_eq!(
+                interpolations(source),
+                [(
+                    vec![TokenKind::Identifier(ident), EQ_EQ, literal],
+                    Span::new(2, 10)
+                )]
+            );
+        }
+        let label = TokenKind::Lifetime(Symbol::intern("a"));
+        assert_eq!(
+            interpolations("f\"{'a'}{'a}\""),
+            [
+                (
+                    vec![TokenKind::Literal(Literal::Char('a'))],
+                    Span::new(2, 5)
+                ),
+                (vec![label], Span::new(7, 4))
+            ]
+        );
+    }
+
+    #[test]
+    fn stray_quote_closes_the_format_string() {
+        let source = "f\"{x\"; let y = 1; }";
+        let (stream, errors) = TokenStream::new(source);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].span(), Some(Span::new(4, 1)));
+        let kinds = stream.tokens().iter().map(Token::kind).collect::<Vec<_>>();
+        assert_eq!(kinds[0], &TokenKind::Format(FormatStr(Vec::new())));
+        assert_eq!(kinds[1..3], [&SEMICOLON, &LET]);
+        assert_eq!(stream.tokens()[1].span(), &Span::new(5, 1));
+        let (stream, errors) = TokenStream::new("f\"{\"a\n\"}\"");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].span(), Some(Span::new(3, 1)));
+        assert_eq!(
+            stream.tokens()[1].kind(),
+            &TokenKind::Identifier(Symbol::intern("a"))
+        );
+    }
+
+    /// String literals the source lexes to, along with their spans
+    fn strings(source: &str) -> Vec<(&str, Span)> {
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        stream
+            .tokens()
+            .iter()
+            .filter_map(|token| match token.kind() {
+                TokenKind::Literal(Literal::Str(str)) => Some((str.as_str(), *token.span())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn raw_strings_keep_backslashes_and_quotes() {
+        assert_eq!(strings("r\"a\\b\""), [("a\\b", Span::new(0, 6))]);
+        assert_eq!(
+            strings("x = r##\"a\"#b\"##;"),
+            [("a\"#b", Span::new(4, 11))]
+        );
+        assert_eq!(strings("r#\"\n\"#"), [("\n", Span::new(0, 6))]);
+    }
+
+    #[test]
+    fn unterminated_raw_string() {
+        let (stream, errors) = TokenStream::new("a r##\"b\"#\nc");
+        assert_eq!(
+            errors,
+            [Error::Unterminated {
+                found: CompactString::from("raw string literal"),
+                span: Some(Span::new(2, 4)),
+            }]
+        );
+        assert_eq!(stream.tokens().len(), 1);
+    }
+
+    #[test]
+    fn identifiers_are_spanned_in_bytes() {
+        assert_eq!(
+            lex("名前 = 1"),
+            [
+                (TokenKind::Identifier(Symbol::intern("名前")), 0),
+                (EQ, 7),
+                (TokenKind::Literal(Literal::Int(1, None)), 9)
+            ]
+        );
+        let (stream, _) = TokenStream::new("名前");
+        assert_eq!(stream.tokens()[0].span(), &Span::new(0, 6));
+    }
+
+    #[test]
+    fn identifiers_begin_with_xid_start() {
+        // A combining accent may continue an identifier but not begin it
+        let (stream, errors) = TokenStream::new("a\u{301} \u{301}b");
+        assert_eq!(
+            errors,
+            [Error::Unknown {
+                found: '\u{301}',
+                span: Some(Span::new(4, 2)),
+            }]
+        );
+        let tokens = stream
+            .tokens()
+            .iter()
+            .map(|token| (token.kind(), *token.span()));
+        assert_eq!(
+            tokens.collect::<Vec<_>>(),
+            [
+                (&TokenKind::identifier("a\u{301}"), Span::new(0, 3)),
+                (&TokenKind::Identifier(Symbol::intern("b")), Span::new(6, 1))
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "nfc")]
+    fn identifiers_are_normalized_to_nfc() {
+        let tokens = lex("e\u{301} \u{e9}");
+        assert_eq!(tokens[0].0, TokenKind::Identifier(Symbol::intern("\u{e9}")));
+        assert_eq!(tokens[0].0, tokens[1].0);
+        assert_eq!(tokens[1].1, 4);
+    }
+
+    #[test]
+    fn lifetimes_and_char_literals() {
+        let lifetime = TokenKind::lifetime;
+        assert_eq!(lex("'a'"), [(TokenKind::Literal(Literal::Char('a')), 0)]);
+        assert_eq!(
+            lex("'a b"),
+            [(lifetime("a"), 0), (TokenKind::identifier("b"), 3)]
+        );
+        assert_eq!(lex("&'static"), [(AMP, 0), (lifetime("static"), 1)]);
+        assert_eq!(lex("'_>"), [(lifetime("_"), 0), (CLS_CHEVRON, 2)]);
+        let (stream, errors) = TokenStream::new("'ab'");
+        assert_eq!(
+            errors.iter().map(Error::span).collect::<Vec<_>>(),
+            [Some(Span::new(0, 4))]
+        );
+        assert!(matches!(
+            stream.tokens()[0].kind(),
+            TokenKind::Literal(Literal::Char('a'))
+        ));
+    }
+
+    #[test]
+    fn format_string_parts() {
+        let source = "f\"a\\t{{{b + 1:>4}}} {c}\"";
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        let TokenKind::Format(FormatStr(parts)) = stream.tokens()[0].kind() else {
+            panic!("{source} is not a format string literal");
+        };
+        let parts = parts
+            .iter()
+            .map(|part| match part {
+                FormatPart::Text(text, span) => (text.to_string(), None, *span),
+                FormatPart::Expr(tokens, spec, span) => {
+                    (tokens.to_string(), spec.map(|spec| spec.to_string()), *span)
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            [
+                (String::from("a\t{"), None, Span::new(2, 5)),
+                (
+                    String::from("b (id) #8..+1, Plus (punct) #10..+1, 1 (integer) (lit) #12..+1"),
+                    Some(String::from(">4")),
+                    Span::new(7, 10)
+                ),
+                (String::from("} "), None, Span::new(17, 3)),
+                (String::from("c (id) #21..+1"), None, Span::new(20, 3)),
+            ]
+        );
+        assert_eq!(*stream.tokens()[0].span(), Span::new(0, source.len()));
     }
 }
@@ -17,6 +17,7 @@
 use crate::*;
 
 #[derive(Copy, Clone, PartialEq, TokenParser, Display, Debug)]
+#[word]
 pub enum Keyword {
     /// Set declaration as public
     #[token = "pub"]
@@ -47,7 +48,7 @@ pub enum Keyword {
     Trait,
 
     /// Refer to the type
-    #[token = "self"]
+    #[token = "Self"]
     SelfTy,
 
     /// Refer to self parameter
@@ -122,3 +123,38 @@ pub enum Keyword {
     #[token = "return"]
     Return,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kinds of the tokens the source lexes to
+    fn kinds(source: &str) -> Vec<TokenKind<'_>> {
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        stream
+            .tokens()
+            .iter()
+            .map(|token| token.kind().clone())
+            .collect()
+    }
+
+    #[test]
+    fn keywords_are_whole_words() {
+        for word in [
+            "letter",
+            "format",
+            "information",
+            "trueish",
+            "fn_x",
+            "r",
+            "f",
+        ] {
+            assert_eq!(kinds(word), [TokenKind::identifier(word)], "{word}");
+        }
+        assert_eq!(
+            kinds("let for in fn true"),
+            [LET, FOR, IN, FN, TokenKind::Literal(Literal::Bool(true))]
+        );
+    }
+}
@@ -15,113 +15,215 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod comment;
+pub mod format;
 pub mod input;
 pub mod keyword;
 pub mod literal;
 pub mod punctuation;
+pub mod relex;
 
 pub use comment::*;
+pub use format::*;
 pub use input::*;
 pub use keyword::*;
 pub use literal::*;
 pub use punctuation::*;
 
 use kiban_commons::*;
+use kiban_error::*;
 use kiban_lexer_derive::TokenParser;
 
-use core::panic;
 use std::{
+    borrow::Cow,
     fmt::Display,
     mem::discriminant,
     ops::{Range, RangeFrom},
+    sync::Arc,
 };
 
 use chumsky::input::{Input as ParserInput, SliceInput, ValueInput};
 use compact_str::{CompactString, ToCompactString};
-use crossbeam::queue::SegQueue;
 use derive_more::{Constructor, Display};
-use itertools::Itertools;
-use scoped_threadpool::Pool;
+use getset::Getters;
 use smallvec::SmallVec;
+use unicode_xid::UnicodeXID;
 
 pub trait TokenOrigin {
-    fn origin(&self) -> Option<CompactString>;
+    fn origin(&self) -> Option<Symbol>;
 }
 
-/// Token stream with recursive info
+/// Token stream with recursive info, which is a view over a shared token buffer so cloning, iterating and slicing it are cheap
+///
+/// Lossless streams also keep what is needed to rebuild their source
 #[derive(Clone, Default, Debug)]
-pub struct TokenStream<'i>(SVec<Token<'i>>, Option<usize>);
+pub struct TokenStream<'i> {
+    buffer: Arc<[Token<'i>]>,
+    start: usize,
+    end: usize,
+    info: Option<usize>,
+    lossless: Option<Lossless<'i>>,
+}
 
 /// Localised token
-#[derive(Copy, Clone, Constructor, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Getters, Debug)]
+#[get = "pub"]
 pub struct Token<'i> {
     kind: TokenKind<'i>,
     span: Span,
+    trivia: Trivia<'i>,
+}
+
+/// Source text around a token that does not belong to any token, which is only kept by lossless streams
+///
+/// Trailing trivia goes up to the end of the token's line, the rest belongs to the next token
+#[derive(Copy, Clone, PartialEq, Getters, Default, Debug)]
+#[get = "pub"]
+pub struct Trivia<'i> {
+    leading: &'i str,
+    trailing: &'i str,
+}
+
+/// Source of a lossless stream and the trivia after its last token
+#[derive(Copy, Clone, Debug)]
+struct Lossless<'i> {
+    source: &'i str,
+    eof: &'i str,
 }
 
 /// Token kinds
-#[derive(Copy, Clone, PartialEq, Display, Debug)]
-#[display(fmt = "{}")]
+#[derive(Clone, PartialEq, Display, Debug)]
 pub enum TokenKind<'i> {
     #[display(fmt = "{} (id)", _0)]
-    Identifier(&'i str),
+    Identifier(Symbol),
+    #[display(fmt = "'{} (lifetime)", _0)]
+    Lifetime(Symbol),
     #[display(fmt = "{} (kw)", _0)]
     Keyword(Keyword),
     #[display(fmt = "{} (punct)", _0)]
     Punctuation(Punctuation),
     #[display(fmt = "{} (lit)", _0)]
-    Literal(Literal<'i>),
+    Literal(Literal),
+    #[display(fmt = "{} (lit)", _0)]
+    Format(FormatStr<'i>),
     #[display(fmt = "{}", _0)]
     Comment(Comment<'i>),
-    #[display(fmt = "{} (unknown)", _0)]
-    Unknown(char),
 }
 
 impl Spanned for TokenStream<'_> {
     fn span(&self) -> Span {
-        if let (Some(start), Some(end)) = (self.0.first(), self.0.last()) {
-            Span::new(
-                *start.span.offset(),
-                (end.span.offset() + end.span.length()) - start.span.offset(),
-            )
+        if let (Some(start), Some(end)) = (self.tokens().first(), self.tokens().last()) {
+            Span::from_combination(start.span, end.span)
         } else {
-            panic!("There is token stream to calculate span!")
+            Span::default()
         }
     }
 }
 
 impl PartialEq for TokenStream<'_> {
     fn eq(&self, other: &Self) -> bool {
-        if let (Some(info_self), Some(info_other)) = (self.1.clone(), other.1.clone()) {
-            info_self == info_other && self.0 == other.0
+        if let (Some(info_self), Some(info_other)) = (self.info, other.info) {
+            info_self == info_other && self.tokens() == other.tokens()
         } else {
-            self.0 == other.0
+            self.tokens() == other.tokens()
         }
     }
 }
 
 impl<'i> PartialEq<Token<'i>> for TokenStream<'i> {
     fn eq(&self, t: &Token) -> bool {
-        if let Some(Token { kind: token, .. }) = self.0.first() {
-            return ((discriminant(token) == discriminant(&t.kind)) && {
-                match token {
-                    TokenKind::Identifier(_) => true,
-                    _ => false,
-                }
-            }) || *token == t.kind;
+        if let Some(Token { kind: token, .. }) = self.tokens().first() {
+            ((discriminant(token) == discriminant(&t.kind))
+                && matches!(token, TokenKind::Identifier(_) | TokenKind::Lifetime(_)))
+                || *token == t.kind
         } else {
-            return false;
+            false
+        }
+    }
+}
+
+impl<'i> Token<'i> {
+    pub fn new(kind: TokenKind<'i>, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            trivia: Trivia::default(),
+        }
+    }
+}
+
+impl<'i> TokenKind<'i> {
+    /// Builds an interned identifier, which is normalized to NFC when the `nfc` feature is enabled
+    pub fn identifier(ident: &str) -> Self {
+        Self::Identifier(Symbol::intern(&Self::normalize(ident)))
+    }
+
+    /// Builds a lifetime from its name, which is interned and normalized like identifiers
+    pub fn lifetime(name: &str) -> Self {
+        Self::Lifetime(Symbol::intern(&Self::normalize(name)))
+    }
+
+    fn normalize(ident: &str) -> Cow<'_, str> {
+        #[cfg(feature = "nfc")]
+        {
+            use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+            if is_nfc_quick(ident.chars()) != IsNormalized::Yes {
+                let normalized = ident.nfc().collect::<String>();
+                if normalized != ident {
+                    return Cow::Owned(normalized);
+                }
+            }
+        }
+        Cow::Borrowed(ident)
+    }
+}
+
+impl<'i> TokenStream<'i> {
+    /// Builds a stream over all the given tokens
+    fn from_tokens(
+        tokens: SVec<Token<'i>>,
+        info: Option<usize>,
+        lossless: Option<Lossless<'i>>,
+    ) -> Self {
+        Self {
+            end: tokens.len(),
+            buffer: Arc::from(tokens.into_vec()),
+            start: 0,
+            info,
+            lossless,
         }
     }
+
+    /// Tokens within the stream, comments included
+    pub fn tokens(&self) -> &[Token<'i>] {
+        &self.buffer[self.start..self.end]
+    }
+
+    /// Token of the buffer at the given offset when it is within the stream
+    fn get(&self, offset: usize) -> Option<&Token<'i>> {
+        (self.start..self.end)
+            .contains(&offset)
+            .then(|| &self.buffer[offset])
+    }
+
+    /// Offset of the first token from the given one that is not a comment, as parsers do not see comments
+    fn skip_comments(&self, offset: usize) -> usize {
+        self.buffer[..self.end]
+            .get(offset..)
+            .and_then(|rest| {
+                rest.iter()
+                    .position(|token| !matches!(token.kind, TokenKind::Comment(_)))
+            })
+            .map_or(self.end.max(offset), |position| offset + position)
+    }
 }
 
 impl<'i> Iterator for TokenStream<'i> {
     type Item = Token<'i>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let value = self.0.first().cloned();
+        let value = self.get(self.start).cloned();
         if value.is_some() {
-            self.0.remove(0);
+            self.start += 1;
         };
         value
     }
@@ -133,7 +235,7 @@ impl<'i> ParserInput<'i> for TokenStream<'i> {
     type Span = Span;
 
     fn start(&self) -> Self::Offset {
-        0
+        self.start
     }
 
     type TokenMaybe = TokenKind<'i>;
@@ -143,11 +245,21 @@ impl<'i> ParserInput<'i> for TokenStream<'i> {
     }
 
     unsafe fn span(&self, rng: Range<Self::Offset>) -> Self::Span {
-        let _start_token = self.0.get(rng.start).unwrap();
-        Span::from_combination(
-            _start_token.span,
-            self.0.get(rng.end).unwrap_or(_start_token).span,
-        )
+        let first = self.skip_comments(rng.start);
+        match (
+            self.get(first),
+            rng.end.checked_sub(1).and_then(|end| self.get(end)),
+        ) {
+            (Some(start), Some(end)) if first < rng.end => {
+                Span::from_combination(start.span, end.span)
+            }
+            (Some(start), _) => Span::new(*start.span.offset(), 0),
+            // The range is past the last token so it points to the end of the stream
+            _ => self.buffer[..self.end]
+                .last()
+                .map(|last| Span::new(last.span.offset() + last.span.length(), 0))
+                .unwrap_or_default(),
+        }
     }
 
     fn prev(offset: Self::Offset) -> Self::Offset {
@@ -157,8 +269,9 @@ impl<'i> ParserInput<'i> for TokenStream<'i> {
 
 impl<'i> ValueInput<'i> for TokenStream<'i> {
     unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
-        if let Some(Token { kind, .. }) = self.0.get(offset) {
-            (offset + 1, Some(*kind))
+        let offset = self.skip_comments(offset);
+        if let Some(Token { kind, .. }) = self.get(offset) {
+            (offset + 1, Some(kind.clone()))
         } else {
             (offset, None)
         }
@@ -169,45 +282,55 @@ impl<'i> SliceInput<'i> for TokenStream<'i> {
     type Slice = TokenStream<'i>;
 
     fn slice(&self, rng: Range<Self::Offset>) -> Self::Slice {
-        TokenStream(
-            self.0.get(rng.clone()).unwrap().into(),
-            self.1.map(|offset| offset + rng.start),
-        )
+        assert!(
+            self.start <= rng.start && rng.start <= rng.end && rng.end <= self.end,
+            "The slice is out of the stream!"
+        );
+        TokenStream {
+            buffer: self.buffer.clone(),
+            start: rng.start,
+            end: rng.end,
+            info: self.info.map(|offset| offset + rng.start - self.start),
+            lossless: self
+                .lossless
+                .map(|lossless| lossless.slice(rng.end == self.buffer.len())),
+        }
     }
 
     fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
-        TokenStream(
-            self.0.get(from.clone()).unwrap().into(),
-            self.1.map(|offset| offset + from.start),
-        )
+        self.slice(from.start..self.end)
     }
 }
 
-impl<'i> Into<TokenStream<'i>> for Token<'i> {
-    fn into(self) -> TokenStream<'i> {
-        TokenStream(SmallVec::from(vec![self]), None)
+impl<'i> From<Token<'i>> for TokenStream<'i> {
+    fn from(token: Token<'i>) -> Self {
+        TokenStream::from_tokens(SmallVec::from(vec![token]), None, None)
     }
 }
 
-impl<'i> From<TokenStream<'i>> for Token<'i> {
-    fn from(value: TokenStream<'i>) -> Self {
-        if value.0.len() == 1 {
-            value.0.first().unwrap().clone()
+impl<'i> TryFrom<TokenStream<'i>> for Token<'i> {
+    type Error = TokenStream<'i>;
+
+    /// Only token streams with exactly one token can be converted into tokens
+    fn try_from(value: TokenStream<'i>) -> Result<Self, Self::Error> {
+        if let [token] = value.tokens() {
+            Ok(token.clone())
         } else {
-            panic!("Token streams with no or more than one token cannot be converted into tokens!")
+            Err(value)
         }
     }
 }
 
 impl<'i> TokenOrigin for TokenKind<'i> {
-    fn origin(&self) -> Option<CompactString> {
+    fn origin(&self) -> Option<Symbol> {
         match self {
-            Self::Identifier(ident) => Some(ident.to_compact_string()),
+            Self::Identifier(ident) => Some(*ident),
+            Self::Lifetime(name) => Some(Symbol::intern(&format!("'{}", name))),
             Self::Keyword(kw) => kw.origin(),
             Self::Punctuation(punc) => punc.origin(),
             Self::Literal(lit) => lit.origin(),
+            Self::Format(format) => format.origin(),
             Self::Comment(..) => None,
-            Self::Unknown(unknown) => Some(unknown.to_compact_string()),
         }
     }
 }
@@ -217,7 +340,7 @@ impl<'i> Display for TokenStream<'i> {
         write!(
             f,
             "{}",
-            self.0
+            self.tokens()
                 .iter()
                 .map(|s| format!("{} #{}", s.kind, s.span))
                 .collect::<Vec<String>>()
@@ -225,3 +348,52 @@ impl<'i> Display for TokenStream<'i> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_characters_are_reported_and_skipped() {
+        let (stream, errors) = TokenStream::new("a $ b €");
+        assert_eq!(
+            errors,
+            [
+                Error::Unknown {
+                    found: '$',
+                    span: Some(Span::new(2, 1)),
+                },
+                Error::Unknown {
+                    found: '€',
+                    span: Some(Span::new(6, 3)),
+                }
+            ]
+        );
+        let spans = stream.tokens().iter().map(|token| *token.span());
+        assert_eq!(
+            spans.collect::<Vec<_>>(),
+            [Span::new(0, 1), Span::new(4, 1)]
+        );
+    }
+
+    #[test]
+    fn unterminated_tokens_are_reported_where_they_begin() {
+        for (source, found, span) in [
+            ("a /* b /* c */", "block comment", Span::new(2, 2)),
+            ("a \"b\n c", "string literal", Span::new(2, 1)),
+            ("a f\"{b}", "format string literal", Span::new(2, 2)),
+            ("a '+", "char literal", Span::new(2, 1)),
+        ] {
+            let (stream, errors) = TokenStream::new(source);
+            assert_eq!(
+                errors,
+                [Error::Unterminated {
+                    found: CompactString::from(found),
+                    span: Some(span),
+                }],
+                "{source:?}"
+            );
+            assert_eq!(stream.tokens()[0].span(), &Span::new(0, 1));
+        }
+    }
+}
@@ -16,58 +16,413 @@
 
 use crate::*;
 
-use std::mem::discriminant;
+use std::{mem::discriminant, num::IntErrorKind};
 
 /// Tokens that store a literal
-#[derive(Copy, Clone, Display, Debug)]
-pub enum Literal<'i> {
+#[derive(Clone, Display, Debug)]
+pub enum Literal {
     #[display(fmt = "{} (bool)", _0)]
     Bool(bool),
     #[display(fmt = "{} (integer)", _0)]
-    Int(usize),
+    Int(u64, Option<Number>),
     #[display(fmt = "{} (float)", _0)]
-    Float(f32),
-    #[display(fmt = "{} (char)", _0)]
-    Char(&'i str),
-    #[display(fmt = "{} (str)", _0)]
-    Str(&'i str),
+    Float(f64, Option<Number>),
+    #[display(fmt = "{:?} (char)", _0)]
+    Char(char),
+    #[display(fmt = "{:?} (str)", _0)]
+    Str(Symbol),
 }
 
-impl<'i> Lexeme<'i> for Literal<'i> {
+impl<'i> Lexeme<'i> for Literal {
     fn parse(s: &mut Fragment) -> Option<Token<'i>> {
-        if let Some(span) = s.consume_pattern("true") {
+        if let Some(span) = s.consume_word("true") {
             Some(Token::new(TokenKind::Literal(Self::Bool(true)), span))
-        } else if let Some(span) = s.consume_pattern("false") {
-            Some(Token::new(TokenKind::Literal(Self::Bool(false)), span))
-        } else if let Some(((is_decimal, number), span)) = s.consume_number() {
-            Some(Token::new(
-                TokenKind::Literal(if !is_decimal {
-                    Self::Int(number.parse().unwrap())
-                } else {
-                    Self::Float(number.parse().unwrap())
-                }),
-                span,
-            ))
         } else {
-            None
+            s.consume_word("false")
+                .map(|span| Token::new(TokenKind::Literal(Self::Bool(false)), span))
         }
     }
 }
 
-impl PartialEq for Literal<'_> {
+impl Literal {
+    /// Lexes a numeric literal with its optional radix prefix and type suffix, malformed or overflowing numbers are reported
+    pub fn number<'i>(s: &mut Fragment<'i>, errors: &mut Vec<Error>) -> Option<Token<'i>> {
+        let ((number, suffix), span) = s.consume_number()?;
+        let digits = number.replace('_', "");
+        let (radix, body) = match digits.get(..2) {
+            Some("0x") => (16, &digits[2..]),
+            Some("0o") => (8, &digits[2..]),
+            Some("0b") => (2, &digits[2..]),
+            _ => (10, digits.as_str()),
+        };
+        let ty = match suffix {
+            "" => Ok(None),
+            "u8" => Ok(Some((false, Number::new(false, Size::_8)))),
+            "u16" => Ok(Some((false, Number::new(false, Size::_16)))),
+            "u32" => Ok(Some((false, Number::new(false, Size::_32)))),
+            "u64" => Ok(Some((false, Number::new(false, Size::_64)))),
+            "i8" => Ok(Some((false, Number::new(true, Size::_8)))),
+            "i16" => Ok(Some((false, Number::new(true, Size::_16)))),
+            "i32" => Ok(Some((false, Number::new(true, Size::_32)))),
+            "i64" => Ok(Some((false, Number::new(true, Size::_64)))),
+            "f32" => Ok(Some((true, Number::new(true, Size::_32)))),
+            "f64" => Ok(Some((true, Number::new(true, Size::_64)))),
+            _ if suffix.starts_with(['e', 'E']) && radix == 10 => {
+                Err("Exponents need at least one digit, like `1e-9`")
+            }
+            _ => Err("Valid suffixes are `u8`, `u16`, `u32`, `u64`, `i8`, `i16`, `i32`, `i64`, `f32` and `f64`"),
+        };
+        let is_float =
+            (radix == 10 && body.contains(['.', 'e', 'E'])) || matches!(ty, Ok(Some((true, _))));
+        let literal = ty.and_then(|ty| {
+            let number = ty.map(|(_, number)| number);
+            if is_float {
+                if radix != 10 {
+                    return Err("Float literals can only be written in decimal");
+                } else if matches!(ty, Some((false, _))) {
+                    return Err("Float literals cannot have an integer suffix");
+                }
+                let value: f64 = body.parse().unwrap();
+                let size = number.map_or(Size::_64, |number| *number.size());
+                if value.is_infinite() || (size == Size::_32 && (value as f32).is_infinite()) {
+                    Err("Float literal is out of the range of its type")
+                } else {
+                    Ok(Self::Float(value, number))
+                }
+            } else {
+                let value = match u64::from_str_radix(body, radix) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        return Err(match error.kind() {
+                            IntErrorKind::Empty => "Integer literals need at least one digit",
+                            IntErrorKind::InvalidDigit => match radix {
+                                16 => "Hexadecimal literals can only hold digits from `0` to `f`",
+                                8 => "Octal literals can only hold digits from `0` to `7`",
+                                2 => "Binary literals can only hold the digits `0` and `1`",
+                                _ => "Decimal literals can only hold digits from `0` to `9`",
+                            },
+                            _ => "Integer literal does not fit in 64 bits",
+                        })
+                    }
+                };
+                // Signed literals may reach the magnitude of their minimum so they can be negated
+                let max = number.map_or(u64::MAX, |number| {
+                    let bits = number.size().bits();
+                    if *number.signed() {
+                        1 << (bits - 1)
+                    } else {
+                        u64::MAX >> (64 - bits)
+                    }
+                });
+                if value > max {
+                    Err("Integer literal is out of the range of its type")
+                } else {
+                    Ok(Self::Int(value, number))
+                }
+            }
+        });
+        Some(Token::new(
+            TokenKind::Literal(literal.unwrap_or_else(|help| {
+                errors.push(Error::Literal {
+                    found: CompactString::from(format!("{}{}", number, suffix)),
+                    help: Some(CompactString::from(help)),
+                    span: Some(span),
+                });
+                if is_float {
+                    Self::Float(0.0, None)
+                } else {
+                    Self::Int(0, None)
+                }
+            })),
+            span,
+        ))
+    }
+
+    /// Builds a char literal from the content between its quotes, which must decode to exactly one character
+    pub fn char(content: &str, offset: usize, errors: &mut Vec<Error>) -> Self {
+        let reported = errors.len();
+        let decoded = Self::unescape(content, offset, errors);
+        let mut chars = decoded.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Self::Char(ch),
+            (Some(ch), Some(_)) if errors.len() != reported => Self::Char(ch),
+            (first, _) => {
+                errors.push(Error::Literal {
+                    found: CompactString::from(format!("'{}'", content)),
+                    help: Some(CompactString::from(if first.is_none() {
+                        "Char literals cannot be empty"
+                    } else {
+                        "Char literals must hold exactly one character, use a string literal instead"
+                    })),
+                    span: Some(Span::new(offset - 1, content.len() + 2)),
+                });
+                Self::Char(first.unwrap_or_default())
+            }
+        }
+    }
+
+    /// Builds a string literal from the content between its quotes
+    pub fn str(content: &str, offset: usize, errors: &mut Vec<Error>) -> Self {
+        Self::Str(Symbol::intern(&Self::unescape(content, offset, errors)))
+    }
+
+    /// Decodes the escape sequences of a quoted literal's content which begins at `offset`, invalid sequences are reported and kept verbatim
+    pub fn unescape(content: &str, offset: usize, errors: &mut Vec<Error>) -> CompactString {
+        let (mut decoded, mut chars) =
+            (CompactString::default(), content.char_indices().peekable());
+        while let Some((start, ch)) = chars.next() {
+            if ch != '\\' {
+                decoded.push(ch);
+                continue;
+            }
+            let escaped = match chars.next().map(|(_, ch)| ch) {
+                Some('n') => Ok('\n'),
+                Some('r') => Ok('\r'),
+                Some('t') => Ok('\t'),
+                Some('0') => Ok('\0'),
+                Some('\\') => Ok('\\'),
+                Some('\'') => Ok('\''),
+                Some('"') => Ok('"'),
+                Some('x') => {
+                    let (mut value, mut digits) = (0_u32, 0_usize);
+                    while digits < 2 {
+                        if let Some((_, digit)) = chars.next_if(|(_, ch)| ch.is_ascii_hexdigit()) {
+                            value = value * 16 + digit.to_digit(16).unwrap();
+                            digits += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    if digits != 2 {
+                        Err("Byte escapes are written with exactly two hexadecimal digits, like `\\x7F`")
+                    } else if value > 0x7F {
+                        Err("Byte escapes can only hold ASCII values, use `\\u{..}` for higher code points")
+                    } else {
+                        Ok(char::from_u32(value).unwrap())
+                    }
+                }
+                Some('u') => {
+                    let (mut value, mut digits) = (0_u32, 0_usize);
+                    let opened = chars.next_if(|(_, ch)| *ch == '{').is_some();
+                    while let Some((_, digit)) = chars.next_if(|(_, ch)| ch.is_ascii_hexdigit()) {
+                        value = value.saturating_mul(16).saturating_add(digit.to_digit(16).unwrap());
+                        digits += 1;
+                    }
+                    let closed = opened && chars.next_if(|(_, ch)| *ch == '}').is_some();
+                    if !closed || digits == 0 || digits > 6 {
+                        Err("Unicode escapes are written with one to six hexadecimal digits between braces, like `\\u{3042}`")
+                    } else if let Some(ch) = char::from_u32(value) {
+                        Ok(ch)
+                    } else {
+                        Err("Unicode escapes must be a valid code point outside of the surrogate range")
+                    }
+                }
+                _ => Err("Valid escapes are `\\n`, `\\r`, `\\t`, `\\0`, `\\\\`, `\\'`, `\\\"`, `\\x..` and `\\u{..}`"),
+            };
+            let end = chars.peek().map_or(content.len(), |(end, _)| *end);
+            match escaped {
+                Ok(ch) => decoded.push(ch),
+                Err(help) => {
+                    let sequence = content.get(start..end).unwrap();
+                    errors.push(Error::Escape {
+                        found: CompactString::from(sequence),
+                        help: Some(CompactString::from(help)),
+                        span: Some(Span::new(offset + start, end - start)),
+                    });
+                    decoded.push_str(sequence);
+                }
+            }
+        }
+        decoded
+    }
+}
+
+impl PartialEq for Literal {
     fn eq(&self, other: &Self) -> bool {
         discriminant(self) == discriminant(other)
     }
 }
 
-impl TokenOrigin for Literal<'_> {
-    fn origin(&self) -> Option<CompactString> {
+impl TokenOrigin for Literal {
+    fn origin(&self) -> Option<Symbol> {
         Some(match self {
-            Self::Bool(bool) => bool.to_compact_string(),
-            Self::Int(int) => int.to_compact_string(),
-            Self::Float(float) => float.to_compact_string(),
-            Self::Char(ch) => ch.to_compact_string(),
-            Self::Str(str) => str.to_compact_string(),
+            Self::Bool(bool) => Symbol::intern(&bool.to_compact_string()),
+            Self::Int(int, _) => Symbol::intern(&int.to_compact_string()),
+            Self::Float(float, _) => Symbol::intern(&float.to_compact_string()),
+            Self::Char(ch) => Symbol::intern(&ch.to_compact_string()),
+            Self::Str(str) => *str,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes the content as if it began at offset 10, along the spans of the errors
+    fn unescape(content: &str) -> (CompactString, Vec<Option<Span>>) {
+        let mut errors = Vec::new();
+        let decoded = Literal::unescape(content, 10, &mut errors);
+        (decoded, errors.iter().map(Error::span).collect())
+    }
+
+    /// Builds the char literal of the content as if its opening quote was at offset 9
+    fn char(content: &str) -> (char, Vec<Option<Span>>) {
+        let mut errors = Vec::new();
+        let Literal::Char(ch) = Literal::char(content, 10, &mut errors) else {
+            unreachable!()
+        };
+        (ch, errors.iter().map(Error::span).collect())
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(unescape("a\\nb"), ("a\nb".into(), vec![]));
+        assert_eq!(unescape("\\\\"), ("\\".into(), vec![]));
+        assert_eq!(unescape("\\0"), ("\0".into(), vec![]));
+        assert_eq!(unescape("\\x7F"), ("\x7F".into(), vec![]));
+        assert_eq!(unescape("\\u{3042}"), ("あ".into(), vec![]));
+        assert_eq!(unescape("\\'\\\""), ("'\"".into(), vec![]));
+    }
+
+    #[test]
+    fn invalid_escape_sequences_are_kept_verbatim() {
+        assert_eq!(
+            unescape("a\\x80"),
+            ("a\\x80".into(), vec![Some(Span::new(11, 4))])
+        );
+        assert_eq!(
+            unescape("\\u{D800}b"),
+            ("\\u{D800}b".into(), vec![Some(Span::new(10, 8))])
+        );
+        assert_eq!(
+            unescape("\\q\\n"),
+            ("\\q\n".into(), vec![Some(Span::new(10, 2))])
+        );
+        assert_eq!(
+            unescape("\\x7"),
+            ("\\x7".into(), vec![Some(Span::new(10, 3))])
+        );
+        assert_eq!(
+            unescape("\\u{}"),
+            ("\\u{}".into(), vec![Some(Span::new(10, 4))])
+        );
+    }
+
+    #[test]
+    fn char_literals_hold_one_character() {
+        assert_eq!(char("き"), ('き', vec![]));
+        assert_eq!(char("\\n"), ('\n', vec![]));
+        assert_eq!(char("ab"), ('a', vec![Some(Span::new(9, 4))]));
+        assert_eq!(char(""), ('\0', vec![Some(Span::new(9, 2))]));
+        // An invalid escape is only reported once
+        assert_eq!(char("\\q"), ('\\', vec![Some(Span::new(10, 2))]));
+    }
+
+    #[test]
+    fn unterminated_string_literal() {
+        let (stream, errors) = TokenStream::new("a \"b\nc");
+        assert_eq!(
+            errors,
+            [Error::Unterminated {
+                found: CompactString::from("string literal"),
+                span: Some(Span::new(2, 1)),
+            }]
+        );
+        assert_eq!(stream.tokens().len(), 1);
+    }
+
+    /// Number the whole source lexes to, in its debug form, along the spans of the errors
+    fn number(source: &str) -> (String, Vec<Option<Span>>) {
+        let mut errors = Vec::new();
+        let token = Literal::number(&mut Fragment::new(0, source), &mut errors).unwrap();
+        assert_eq!(*token.span(), Span::new(0, source.len()));
+        let TokenKind::Literal(literal) = token.kind() else {
+            unreachable!()
+        };
+        (
+            format!("{literal:?}"),
+            errors.iter().map(Error::span).collect(),
+        )
+    }
+
+    #[test]
+    fn integer_literals() {
+        assert_eq!(
+            number("0xFF_u8"),
+            (
+                String::from("Int(255, Some(Number { signed: false, size: _8 }))"),
+                vec![]
+            )
+        );
+        assert_eq!(number("1_000"), (String::from("Int(1000, None)"), vec![]));
+        assert_eq!(
+            number("128i8"),
+            (
+                String::from("Int(128, Some(Number { signed: true, size: _8 }))"),
+                vec![]
+            )
+        );
+        let invalid = (String::from("Int(0, None)"), vec![Some(Span::new(0, 5))]);
+        assert_eq!(number("0b102"), invalid);
+        assert_eq!(number("256u8"), invalid);
+        assert_eq!(number("129i8"), invalid);
+        assert_eq!(
+            number("99999999999999999999"),
+            (String::from("Int(0, None)"), vec![Some(Span::new(0, 20))])
+        );
+    }
+
+    #[test]
+    fn float_literals() {
+        assert_eq!(number("1e-9"), (String::from("Float(1e-9, None)"), vec![]));
+        assert_eq!(
+            number("2.5f32"),
+            (
+                String::from("Float(2.5, Some(Number { signed: true, size: _32 }))"),
+                vec![]
+            )
+        );
+        assert_eq!(
+            number("1e"),
+            (String::from("Int(0, None)"), vec![Some(Span::new(0, 2))])
+        );
+        assert_eq!(
+            number("3.0u8"),
+            (
+                String::from("Float(0.0, None)"),
+                vec![Some(Span::new(0, 5))]
+            )
+        );
+        assert_eq!(
+            number("1e400f64"),
+            (
+                String::from("Float(0.0, None)"),
+                vec![Some(Span::new(0, 8))]
+            )
+        );
+    }
+
+    #[test]
+    fn dots_after_integers_are_not_taken() {
+        let (stream, errors) = TokenStream::new("0..9 1.max");
+        assert!(errors.is_empty(), "{errors:?}");
+        let tokens = stream
+            .tokens()
+            .iter()
+            .map(|token| (token.kind().to_string(), *token.span()))
+            .collect::<Vec<_>>();
+        let int = |value: &str, offset| (format!("{value} (integer) (lit)"), Span::new(offset, 1));
+        assert_eq!(
+            tokens,
+            [
+                int("0", 0),
+                (DOT_DOT.to_string(), Span::new(1, 2)),
+                int("9", 3),
+                int("1", 5),
+                (DOT.to_string(), Span::new(6, 1)),
+                (String::from("max (id)"), Span::new(7, 3)),
+            ]
+        );
+    }
+}
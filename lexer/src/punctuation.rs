@@ -16,8 +16,55 @@
 
 use crate::*;
 
+/// Punctuation tokens, the longest one matching the input wins
 #[derive(Copy, Clone, PartialEq, TokenParser, Display, Debug)]
 pub enum Punctuation {
+    #[token = "<<="]
+    LShtEq,
+    #[token = ">>="]
+    RShtEq,
+    #[token = "**="]
+    StarStarEq,
+    #[token = "..="]
+    DotDotEq,
+    #[token = "<<"]
+    LSht,
+    #[token = ">>"]
+    RSht,
+    #[token = "<="]
+    LessEq,
+    #[token = ">="]
+    GrEq,
+    #[token = "=="]
+    EqEq,
+    #[token = "!="]
+    NotEq,
+    #[token = "&&"]
+    And,
+    #[token = "||"]
+    Or,
+    #[token = "**"]
+    StarStar,
+    #[token = "+="]
+    PlusEq,
+    #[token = "-="]
+    LineEq,
+    #[token = "*="]
+    StarEq,
+    #[token = "/="]
+    SlashEq,
+    #[token = "%="]
+    PercEq,
+    #[token = "^="]
+    CaretEq,
+    #[token = "->"]
+    Arrow,
+    #[token = "=>"]
+    FatArrow,
+    #[token = "::"]
+    DoubleColon,
+    #[token = ".."]
+    DotDot,
     #[token = "("]
     OpParen,
     #[token = ")"]
@@ -56,8 +103,8 @@ pub enum Punctuation {
     Amp,
     #[token = "!"]
     Exclm,
-    #[token = "'"]
-    Apos,
+    #[token = "#"]
+    Hash,
     #[token = ","]
     Comma,
     #[token = "."]
@@ -67,3 +114,58 @@ pub enum Punctuation {
     #[token = ";"]
     Semicolon,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kinds of the tokens the source lexes to
+    fn kinds(source: &str) -> Vec<TokenKind<'_>> {
+        let (stream, errors) = TokenStream::new(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        stream
+            .tokens()
+            .iter()
+            .map(|token| token.kind().clone())
+            .collect()
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        assert!(Punctuation::ALL
+            .iter()
+            .all(|punctuation| punctuation.as_str().parse() == Ok(*punctuation)));
+        assert_eq!("<<<".parse::<Punctuation>(), Err(()));
+    }
+
+    #[test]
+    fn longest_token_wins() {
+        // Every token lexes whole, wherever its variant is declared
+        for punctuation in Punctuation::ALL {
+            assert_eq!(
+                kinds(punctuation.as_str()),
+                [TokenKind::Punctuation(*punctuation)],
+                "{punctuation}"
+            );
+        }
+        assert_eq!(
+            kinds("a<<=b"),
+            [
+                TokenKind::identifier("a"),
+                L_SHT_EQ,
+                TokenKind::identifier("b")
+            ]
+        );
+        assert_eq!(
+            kinds("0..=9"),
+            [
+                TokenKind::Literal(Literal::Int(0, None)),
+                DOT_DOT_EQ,
+                TokenKind::Literal(Literal::Int(9, None))
+            ]
+        );
+        assert_eq!(kinds("**=*"), [STAR_STAR_EQ, STAR]);
+        assert_eq!(kinds("<<<="), [L_SHT, LESS_EQ]);
+        assert_eq!(kinds("...="), [DOT_DOT, DOT, EQ]);
+    }
+}
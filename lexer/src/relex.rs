@@ -0,0 +1,156 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::*;
+
+impl<'i> TokenStream<'i> {
+    /// Lexes the source again after replacing the given range of the previous source with the replacement, which the source must already include
+    ///
+    /// Lexing restarts at the start of the token before the last one that ends ahead of the edit, so strings, comments and tokens that look ahead are lexed again as a whole.
+    /// Once a token begins where a token of the previous stream began after the edit, the rest of the previous stream is reused with its spans shifted.
+    /// The errors of the previous stream are updated the same way, so every error of the edited source is returned
+    pub fn relex(
+        previous: &TokenStream<'_>,
+        previous_errors: &[Error],
+        source: &'i str,
+        edit: Range<usize>,
+        replacement: &str,
+    ) -> (Self, Vec<Error>) {
+        debug_assert_eq!(
+            source.get(edit.start..edit.start + replacement.len()),
+            Some(replacement),
+            "The source does not include the replacement"
+        );
+        let restart = previous
+            .tokens()
+            .iter()
+            .rposition(|token| token.span.range().end < edit.start)
+            .map_or(0, |before| before.saturating_sub(1));
+        let mut tokens = previous.tokens()[..restart]
+            .iter()
+            .map(|token| token.rebase(source, *token.span.offset()))
+            .collect::<SVec<_>>();
+        let mut fragment = match previous.tokens().get(restart) {
+            Some(token) if restart != 0 => {
+                let offset = *token.span.offset();
+                Fragment::new(offset, &source[offset..])
+            }
+            _ => Fragment::start(source),
+        };
+        let relexed = *fragment.offset();
+        let mut errors = previous_errors
+            .iter()
+            .filter(|error| error.span().is_none_or(|span| *span.offset() < relexed))
+            .cloned()
+            .collect::<Vec<_>>();
+        let (mut open, edit_end) = (None, edit.start + replacement.len());
+        let mut reusable = restart;
+        while fragment.skip_whitespace() {
+            let offset = *fragment.offset();
+            if offset >= edit_end {
+                let previous_offset = offset - edit_end + edit.end;
+                while previous
+                    .tokens()
+                    .get(reusable)
+                    .is_some_and(|token| *token.span.offset() < previous_offset)
+                {
+                    reusable += 1;
+                }
+                if previous
+                    .tokens()
+                    .get(reusable)
+                    .is_some_and(|token| *token.span.offset() == previous_offset)
+                {
+                    tokens.extend(previous.tokens()[reusable..].iter().map(|token| {
+                        token.rebase(source, *token.span.offset() - edit.end + edit_end)
+                    }));
+                    errors.extend(
+                        previous_errors
+                            .iter()
+                            .filter(|error| {
+                                error
+                                    .span()
+                                    .is_some_and(|span| *span.offset() >= previous_offset)
+                            })
+                            .map(|error| {
+                                error.clone().shift(edit_end as isize - edit.end as isize)
+                            }),
+                    );
+                    break;
+                }
+            }
+            if let Some(token) = fragment.step(true, &mut errors, &mut open) {
+                tokens.push(token);
+            }
+        }
+        let lossless = previous
+            .lossless
+            .map(|_| Lossless::attach(&mut tokens, source));
+        (Self::from_tokens(tokens, previous.info, lossless), errors)
+    }
+}
+
+impl Token<'_> {
+    /// Moves the token to the given offset of the source, borrowing its text from it
+    fn rebase<'i>(&self, source: &'i str, offset: usize) -> Token<'i> {
+        let text = &source[offset..offset + self.span.length()];
+        let kind = match &self.kind {
+            TokenKind::Identifier(ident) => TokenKind::Identifier(*ident),
+            TokenKind::Lifetime(name) => TokenKind::Lifetime(*name),
+            // The content follows the opening delimiter and the doc marker
+            TokenKind::Comment(comment) => {
+                let start = 2 + comment.is_doc() as usize;
+                TokenKind::Comment(Comment::new(
+                    comment.typed,
+                    &text[start..start + comment.content.len()],
+                ))
+            }
+            TokenKind::Keyword(kw) => TokenKind::Keyword(*kw),
+            TokenKind::Punctuation(punc) => TokenKind::Punctuation(*punc),
+            TokenKind::Literal(lit) => TokenKind::Literal(lit.clone()),
+            // Interpolated expressions are moved along with the literal
+            TokenKind::Format(FormatStr(parts)) => {
+                let shift = |span: &Span| {
+                    Span::new(span.offset() - self.span.offset() + offset, *span.length())
+                };
+                TokenKind::Format(FormatStr(
+                    parts
+                        .iter()
+                        .map(|part| match part {
+                            FormatPart::Text(text, span) => FormatPart::Text(*text, shift(span)),
+                            FormatPart::Expr(tokens, spec, span) => FormatPart::Expr(
+                                TokenStream::from_tokens(
+                                    tokens
+                                        .tokens()
+                                        .iter()
+                                        .map(|token| {
+                                            token.rebase(source, *shift(&token.span).offset())
+                                        })
+                                        .collect(),
+                                    None,
+                                    None,
+                                ),
+                                *spec,
+                                shift(span),
+                            ),
+                        })
+                        .collect(),
+                ))
+            }
+        };
+        Token::new(kind, Span::new(offset, *self.span.length()))
+    }
+}
@@ -0,0 +1,170 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use kiban_lexer::TokenStream;
+
+use std::ops::Range;
+
+const SOURCE: &str = r##"/// Sums the values of a list
+pub fn sum(values: &[i64]) -> i64 {
+    let mut total = 0_i64;
+    for value in values {
+        total += *value; // accumulate
+    }
+    /* nested /* block */ comment */
+    total
+}
+
+const GREETING: &str = "こんにちは, \"world\"\n";
+const PATTERN: &str = r#"^\d{4}-\d{2}$"#;
+let ratio = 1.5e-3f64 * 0xFF_u32 as f64 << 2;
+let c = 'a'; let l: &'a str = x..=y;
+log(f"{{total}} = {total:>8}, {values[0] + m("}")}");
+let invalid = "\q" $ 0b12;
+"##;
+
+/// Replacements that open or close strings and comments, or join and split tokens
+const REPLACEMENTS: [&str; 16] = [
+    "", " ", "\n", "\"", "/*", "*/", "//", "'", "r#\"", "\"#", "f\"", "{", "1.", "5", "e+", "ident",
+];
+
+/// Applies the edit to the source
+fn edit(source: &str, range: Range<usize>, replacement: &str) -> String {
+    format!(
+        "{}{}{}",
+        &source[..range.start],
+        replacement,
+        &source[range.end..]
+    )
+}
+
+/// Checks that lexing again after the edit gives the same tokens, with the same values, and errors as lexing the whole edited source
+fn assert_equivalent(source: &str, range: Range<usize>, replacement: &str) {
+    let (previous, previous_errors) = TokenStream::new(source);
+    let edited = edit(source, range.clone(), replacement);
+    let (relexed, errors) = TokenStream::relex(
+        &previous,
+        &previous_errors,
+        &edited,
+        range.clone(),
+        replacement,
+    );
+    let (full, full_errors) = TokenStream::new(&edited);
+    assert!(
+        relexed == full && format!("{:?}", relexed.tokens()) == format!("{:?}", full.tokens()),
+        "Lexing again after replacing {:?} with {:?} differs from a full lex\nrelexed: {}\nfull: {}",
+        range,
+        replacement,
+        relexed,
+        full
+    );
+    assert_eq!(
+        errors, full_errors,
+        "Errors after replacing {:?} with {:?} differ from a full lex",
+        range, replacement
+    );
+}
+
+/// Character boundaries of the source
+fn boundaries(source: &str) -> Vec<usize> {
+    (0..=source.len())
+        .filter(|index| source.is_char_boundary(*index))
+        .collect()
+}
+
+#[test]
+fn insertions() {
+    for offset in boundaries(SOURCE) {
+        for replacement in REPLACEMENTS {
+            assert_equivalent(SOURCE, offset..offset, replacement);
+        }
+    }
+}
+
+#[test]
+fn deletions() {
+    let boundaries = boundaries(SOURCE);
+    for (index, start) in boundaries.iter().enumerate() {
+        for end in boundaries.iter().skip(index).take(8) {
+            assert_equivalent(SOURCE, *start..*end, "");
+        }
+    }
+}
+
+#[test]
+fn replacements() {
+    let boundaries = boundaries(SOURCE);
+    for (index, start) in boundaries.iter().enumerate().step_by(3) {
+        for end in boundaries.iter().skip(index + 1).step_by(5).take(4) {
+            for replacement in REPLACEMENTS {
+                assert_equivalent(SOURCE, *start..*end, replacement);
+            }
+        }
+    }
+}
+
+#[test]
+fn successive_edits() {
+    let (mut source, mut seed) = (SOURCE.to_string(), 0x2545_f491_u64);
+    let (mut stream, mut errors) = TokenStream::new(Box::leak(source.clone().into_boxed_str()));
+    for _ in 0..500 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let boundaries = boundaries(&source);
+        let start = boundaries[(seed >> 33) as usize % boundaries.len()];
+        let end = boundaries
+            .iter()
+            .copied()
+            .filter(|end| *end >= start)
+            .nth((seed >> 20) as usize % 4)
+            .unwrap_or(start);
+        let replacement = REPLACEMENTS[(seed >> 40) as usize % REPLACEMENTS.len()];
+        source = edit(&source, start..end, replacement);
+        let edited: &'static str = Box::leak(source.clone().into_boxed_str());
+        (stream, errors) = TokenStream::relex(&stream, &errors, edited, start..end, replacement);
+        let (full, full_errors) = TokenStream::new(edited);
+        assert_eq!(
+            format!("{:?}", stream.tokens()),
+            format!("{:?}", full.tokens()),
+            "{:?}",
+            edited
+        );
+        assert_eq!(errors, full_errors, "{:?}", edited);
+    }
+}
+
+#[test]
+fn lossless() {
+    let (previous, errors) = TokenStream::lossless(SOURCE);
+    let edited = edit(SOURCE, 40..44, "/* x */");
+    let (relexed, _) = TokenStream::relex(&previous, &errors, &edited, 40..44, "/* x */");
+    assert_eq!(relexed.to_source().as_deref(), Some(edited.as_str()));
+}
+
+#[test]
+fn shebang() {
+    for source in [
+        "#!/usr/bin/env kiban run\nfn main",
+        "\u{feff}#![attr]\nfn main",
+    ] {
+        for offset in boundaries(source) {
+            for replacement in ["", "[", "\n", "#!", "\u{feff}"] {
+                assert_equivalent(source, offset..offset, replacement);
+            }
+        }
+    }
+}
@@ -16,20 +16,20 @@
 
 #![allow(non_snake_case)]
 
-use kiban_lexer::TokenStream;
 use kiban_syntax::*;
 
 use std::{ffi::OsString, fs};
 
-use chumsky::prelude::*;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use miette::Result;
+use miette::{Report, Result};
 
 fn main() -> Result<()> {
-    let input = TokenStream::from("/* hello world! */");
-    println!("{}", input);
-    let parsed = _literal().parse(input);
-    println!("{:?}", parsed);
+    let source = "/* hello world! */";
+    let (syntax, errors) = parse(source);
+    errors
+        .into_iter()
+        .for_each(|error| eprintln!("{:?}", Report::new(error).with_source_code(source)));
+    println!("{:?}", syntax);
     Ok(())
 }
 
@@ -86,9 +86,7 @@ fn _run(
             .to_str()
             .unwrap()
             .to_string(),
-        *command
-            .get_one::<bool>("takeSource")
-            .unwrap_or_else(|| &false),
+        *command.get_one::<bool>("takeSource").unwrap_or(&false),
     );
 
     let (_id, _source, _origin) = (
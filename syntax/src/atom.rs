@@ -18,33 +18,303 @@ use crate::*;
 
 node!(
     #[doc = "Holds an identifier"]
-    Ident<'i>(&'i str)
+    Ident(Symbol) {
+        select! {
+            TokenKind::Identifier(ident) = s => Node::new(_Ident(ident), s),
+        }
+    }
 );
 
 node! {
     #[doc = "Define a path which is composed by an identifier, it's generics and an optional subpath"]
-    Path<'i> {
-        ident: Ident<'i>,
-        generics: Generics<'i>,
-        subpath: Option<Path<'i>>
+    Path {
+        ident: Ident,
+        generics: Generics,
+        subpath: Option<Path>
     }
 }
 
 node! {
     #[doc = "Define generics of a type"]
-    Generics<'i>(SVec<GenericTypes<'i>>)
+    Generics(SVec<GenericTypes>)
 }
 
 node! {
     #[doc = "Define the lifetime of the type"]
-    Lifetime<'i>(Ident<'i>)
+    Lifetime(Ident) {
+        select! {
+            TokenKind::Lifetime(name) = s => {
+                let s: Span = s;
+                let name = Node::new(_Ident(name), Span::new(s.offset() + 1, s.length() - 1));
+                Node::new(_Lifetime(name), s)
+            },
+        }
+    }
 }
 
 node! {
     #[doc = "Define generics of a type"]
-    case GenericTypes<'i> {
-        Lifetime(Lifetime<'i>),
-        Name(Ident<'i>),
-        Type(Type<'i>)
+    case GenericTypes {
+        Lifetime(Lifetime),
+        Name(Ident),
+        Type(Type)
+    }
+}
+
+impl Generics {
+    /// Generics of a path segment which has none, placed right after its identifier
+    pub(crate) fn empty(ident: &Ident) -> Self {
+        let span = ident.span();
+        Node::new(
+            _Generics(SVec::new()),
+            Span::new(span.offset() + span.length(), 0),
+        )
+    }
+}
+
+/// Parses paths, in expressions generics follow a `::` so they are not taken as comparisons
+///
+/// Paths may begin with `self` or `Self`, so receivers and the implemented type can be named
+pub(crate) fn path<'i>(
+    ty: impl NodeParser<'i, Type>,
+    turbofish: bool,
+) -> impl NodeParser<'i, Path> {
+    let generics = match turbofish {
+        true => just(DOUBLE_COLON).ignore_then(generics(ty)).boxed(),
+        false => generics(ty).boxed(),
+    };
+    let segment = |ident: Boxed<'i, 'i, _, Ident, _>, subpath| {
+        ident
+            .then(generics.clone().or_not())
+            .then(just(DOUBLE_COLON).ignore_then(subpath).or_not())
+            .map_with_span(|((ident, generics), subpath), s| {
+                let generics = generics.unwrap_or_else(|| Generics::empty(&ident));
+                Node::new(
+                    _Path {
+                        ident,
+                        generics,
+                        subpath,
+                    },
+                    s,
+                )
+            })
+    };
+    let subpath = recursive(|subpath| segment(_ident().boxed(), subpath));
+    segment(head().boxed(), subpath)
+}
+
+/// Parses the first identifier of a path
+fn head<'i>() -> impl NodeParser<'i, Ident> {
+    choice((
+        _ident(),
+        just(SELF_PARAM).map_with_span(|_, s| Node::new(_Ident(sym::SELF), s)),
+        just(SELF_TY).map_with_span(|_, s| Node::new(_Ident(sym::SELF_TY), s)),
+    ))
+}
+
+/// A node along how many `>` of the token closing its generics are left for the generics enclosing it
+type Owing<T> = (T, usize);
+
+/// Parses generic arguments which are paths with generics, the other ones are parsed as types
+fn nested_path<'i>(
+    generics: impl NodeParser<'i, Owing<Generics>>,
+) -> impl NodeParser<'i, Owing<Path>> {
+    let any = recursive(|any| {
+        _ident()
+            .then(
+                generics
+                    .clone()
+                    .or_not()
+                    .then(just(DOUBLE_COLON).ignore_then(any).or_not()),
+            )
+            .map_with_span(|(ident, (generics, subpath)), s| {
+                nested_segment(ident, generics, subpath, s)
+            })
+    });
+    let listed = |ident: Boxed<'i, 'i, _, Ident, _>, listed| {
+        ident
+            .then(choice((
+                generics
+                    .clone()
+                    .map(Some)
+                    .then(just(DOUBLE_COLON).ignore_then(any.clone()).or_not()),
+                just(DOUBLE_COLON)
+                    .ignore_then(listed)
+                    .map(|subpath| (None, Some(subpath))),
+            )))
+            .map_with_span(|(ident, (generics, subpath)), s| {
+                nested_segment(ident, generics, subpath, s)
+            })
+    };
+    let subpath = recursive(|subpath| listed(_ident().boxed(), subpath));
+    listed(head().boxed(), subpath)
+}
+
+/// Builds a segment of a nested path, which owes what its generics or its subpath do
+fn nested_segment(
+    ident: Ident,
+    generics: Option<Owing<Generics>>,
+    subpath: Option<Owing<Path>>,
+    span: Span,
+) -> Owing<Path> {
+    let (generics, owes) = generics.unwrap_or_else(|| (Generics::empty(&ident), 0));
+    let (subpath, owes) = match subpath {
+        Some((subpath, owes)) => (Some(subpath), owes),
+        None => (None, owes),
+    };
+    let path = _Path {
+        ident,
+        generics,
+        subpath,
+    };
+    (Node::new(path, span), owes)
+}
+
+/// Parses paths without generics, like the ones of attributes
+pub(crate) fn plain_path<'i>() -> impl NodeParser<'i, Path> {
+    _ident()
+        .separated_by(just(DOUBLE_COLON))
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .map_with_span(|idents, s| segments(idents, None, s))
+}
+
+/// Joins identifiers into a path that ends at the span's end, the last one takes the generics
+fn segments(mut idents: Vec<Ident>, generics: Option<Generics>, span: Span) -> Path {
+    let last = idents.pop().unwrap();
+    let path = Node::new(
+        _Path {
+            generics: generics.unwrap_or_else(|| Generics::empty(&last)),
+            ident: last.clone(),
+            subpath: None,
+        },
+        Span::from_combination(last.span(), span),
+    );
+    idents.into_iter().rev().fold(path, |subpath, ident| {
+        Node::new(
+            _Path {
+                generics: Generics::empty(&ident),
+                ident: ident.clone(),
+                subpath: Some(subpath),
+            },
+            Span::from_combination(ident.span(), span),
+        )
+    })
+}
+
+/// Parses the generic parameters a definition declares, which are none without chevrons
+pub(crate) fn generic_params<'i>() -> impl NodeParser<'i, Generics> {
+    choice((
+        _lifetime().map_with_span(|lifetime, s| Node::new(_GenericTypes::Lifetime(lifetime), s)),
+        _ident().map_with_span(|name, s| Node::new(_GenericTypes::Name(name), s)),
+    ))
+    .separated_by(just(COMMA))
+    .allow_trailing()
+    .collect::<Vec<_>>()
+    .delimited_by(just(OP_CHEVRON), just(CLS_CHEVRON))
+    .or_not()
+    .map_with_span(|params, s| Node::new(_Generics(params.unwrap_or_default().into()), s))
+}
+
+/// Parses generic arguments
+pub(crate) fn generics<'i>(ty: impl NodeParser<'i, Type>) -> impl NodeParser<'i, Generics> {
+    let nested = recursive(|nested| arguments(ty.clone(), nested_path(nested), true));
+    arguments(ty, nested_path(nested), false).map(|(generics, _)| generics)
+}
+
+/// Parses generic arguments in a single pass. `>>`, `>=` and `>>=` are single tokens, so when the
+/// generics of a nested argument close with one of them, the `>` left are owed to the enclosing generics,
+/// and the outermost ones leave the `=` of the last two unconsumed
+fn arguments<'i>(
+    ty: impl NodeParser<'i, Type>,
+    path: impl NodeParser<'i, Owing<Path>>,
+    nested: bool,
+) -> impl NodeParser<'i, Owing<Generics>> {
+    let arguments = choice((
+        _lifetime()
+            .map_with_span(|lifetime, s| (Node::new(_GenericTypes::Lifetime(lifetime), s), 0)),
+        path.map_with_span(|(path, owes), s| {
+            let ty = Node::new(_Type::Path(path), s);
+            (Node::new(_GenericTypes::Type(ty), s), owes)
+        }),
+        ty.map_with_span(|ty, s| (Node::new(_GenericTypes::Type(ty), s), 0)),
+    ))
+    .separated_by(just(COMMA))
+    .collect::<Vec<_>>()
+    .boxed();
+    just(OP_CHEVRON)
+        .ignore_then(custom(move |inp| {
+            let arguments = inp.parse(arguments.clone())?;
+            let taken = arguments.last().map_or(0, |(_, owes)| *owes) + 1;
+            let closer = inp.peek().and_then(|token| {
+                [
+                    (CLS_CHEVRON, 1, false),
+                    (R_SHT, 2, false),
+                    (GR_EQ, 1, true),
+                    (R_SHT_EQ, 2, true),
+                ]
+                .into_iter()
+                .find(|(closer, ..)| *closer == token)
+            });
+            let owes = match closer {
+                Some((_, count, false)) if taken == count => {
+                    inp.skip();
+                    0
+                }
+                Some((_, count, eq)) if (nested && taken < count) || (eq && taken == count) => {
+                    taken
+                }
+                _ => inp.parse(just(CLS_CHEVRON).to(0))?,
+            };
+            Ok((arguments, owes))
+        }))
+        .map_with_span(|(arguments, owes), s| {
+            let arguments = arguments.into_iter().map(|(argument, _)| argument);
+            (Node::new(_Generics(arguments.collect()), s), owes)
+        })
+}
+
+/// Parses the `=` after a type, along the span of a `>=` or `>>=` which closed the generics it ends with
+pub(crate) fn assign<'i>() -> impl NodeParser<'i, Option<(TokenKind<'i>, Span)>> {
+    choice((
+        just(EQ).to(None),
+        just(GR_EQ)
+            .or(just(R_SHT_EQ))
+            .map_with_span(|token, s| Some((token, s))),
+    ))
+}
+
+/// Reports the `>=` or `>>=` taken as `=` when it did not close the generics the type ends with
+pub(crate) fn check_assign<'i>(
+    ty: &Type,
+    split: Option<(TokenKind<'i>, Span)>,
+    emitter: &mut chumsky::input::Emitter<ParserError<'i>>,
+) {
+    if let Some((token, span)) = split.filter(|_| !ty.inner().is_some_and(unclosed)) {
+        emitter.emit(ParserError::unexpected(token, [EQ], span));
+    }
+}
+
+/// Whether the type ends with generics whose closing token was left unconsumed
+fn unclosed(ty: &_Type) -> bool {
+    match ty {
+        _Type::Path(path) => {
+            let mut path = path;
+            while let Some(subpath) = path.inner().and_then(|path| path.subpath.as_ref()) {
+                path = subpath;
+            }
+            let Some(generics) = path.inner().map(|path| &path.generics) else {
+                return false;
+            };
+            match generics.inner().and_then(|arguments| arguments.0.last()) {
+                Some(last) => last.span().range().end == generics.span().range().end,
+                None => *generics.span().length() == 1,
+            }
+        }
+        _Type::Ref(_, ty) | _Type::Ptr(ty) => ty
+            .inner()
+            .and_then(|ty| ty.ty.inner())
+            .is_some_and(unclosed),
+        _ => false,
     }
 }
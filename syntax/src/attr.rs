@@ -0,0 +1,192 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::*;
+
+node! {
+    #[doc = "Define a list of attributes"]
+    Attributes(SVec<Attribute>) {
+        attributes(_AttrStyle::Outer)
+    }
+}
+
+node! {
+    #[doc = "Define an attribute, `#[...]` applies to the item that follows it and `#![...]` to the one that encloses it"]
+    Attribute {
+        style: AttrStyle,
+        path: Path,
+        args: SVec<TokenTree>
+    } {
+        attribute(_AttrStyle::Outer).or(attribute(_AttrStyle::Inner))
+    }
+}
+
+/// Parses the attributes of a style, inner ones go at the start of files and blocks
+pub(crate) fn attributes<'i>(style: _AttrStyle) -> impl NodeParser<'i, Attributes> {
+    attribute(style)
+        .repeated()
+        .collect::<Vec<_>>()
+        .map_with_span(|attrs, s| Node::new(_Attributes(attrs.into()), s))
+}
+
+/// Parses an attribute of a style
+fn attribute<'i>(style: _AttrStyle) -> impl NodeParser<'i, Attribute> {
+    let bang = match style {
+        _AttrStyle::Inner => just(EXCLM).ignored().boxed(),
+        _AttrStyle::Outer => empty().boxed(),
+    };
+    just(HASH)
+        .ignore_then(bang)
+        .map_with_span(move |_, s| Node::new(style.clone(), s))
+        .then(
+            plain_path()
+                .then(_tokentree().repeated().collect::<Vec<_>>())
+                .delimited_by(just(OP_SQ_BRACKET), just(CLS_SQ_BRACKET)),
+        )
+        .map_with_span(|(style, (path, args)), s| {
+            Node::new(
+                _Attribute {
+                    style,
+                    path,
+                    args: args.into(),
+                },
+                s,
+            )
+        })
+}
+
+node! {
+    #[doc = "Define the delimiters of a token tree"]
+    case Delimiter {
+        Paren,
+        Bracket,
+        Brace
+    }
+}
+
+node! {
+    #[doc = "Define token trees, which are single tokens or token trees enclosed by balanced delimiters"]
+    case TokenTree {
+        Ident(Ident),
+        Lifetime(Lifetime),
+        Keyword(Keyword),
+        Punctuation(Punctuation),
+        Literal(Literal),
+        Delimited(Delimiter, SVec<TokenTree>),
+    } {
+        recursive(|tree| {
+            let delimited = |open, close, delimiter: _Delimiter| {
+                tree.clone()
+                    .repeated()
+                    .collect::<Vec<_>>()
+                    .delimited_by(just(open), just(close))
+                    .map_with_span(move |trees, s| {
+                        Node::new(
+                            _TokenTree::Delimited(Node::new(delimiter.clone(), s), trees.into()),
+                            s,
+                        )
+                    })
+            };
+            choice((
+                delimited(OP_PAREN, CLS_PAREN, _Delimiter::Paren),
+                delimited(OP_SQ_BRACKET, CLS_SQ_BRACKET, _Delimiter::Bracket),
+                delimited(OP_BRACE, CLS_BRACE, _Delimiter::Brace),
+                _ident().map_with_span(|ident, s| Node::new(_TokenTree::Ident(ident), s)),
+                _lifetime().map_with_span(|lifetime, s| Node::new(_TokenTree::Lifetime(lifetime), s)),
+                _literal().map_with_span(|literal, s| Node::new(_TokenTree::Literal(literal), s)),
+                select! {
+                    TokenKind::Keyword(keyword) = s => Node::new(_TokenTree::Keyword(keyword), s),
+                    TokenKind::Punctuation(punctuation) = s if !matches!(
+                        punctuation,
+                        Punctuation::OpParen
+                            | Punctuation::ClsParen
+                            | Punctuation::OpSqBracket
+                            | Punctuation::ClsSqBracket
+                            | Punctuation::OpBrace
+                            | Punctuation::ClsBrace
+                    ) => Node::new(_TokenTree::Punctuation(punctuation), s),
+                },
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Names and styles of the attributes
+    fn names(attrs: &Attributes) -> Vec<(&'static str, _AttrStyle)> {
+        attrs
+            .inner()
+            .unwrap()
+            .0
+            .iter()
+            .map(|attr| {
+                let attr = attr.inner().unwrap();
+                let name = attr.path.inner().unwrap().ident.inner().unwrap().0;
+                (name.as_str(), attr.style.inner().unwrap().clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inner_attributes_belong_to_files_and_blocks() {
+        let (syntax, errors) = parse("#![crate_attr]\n#[test] fn f() { #![inline] #[cold] g(); }");
+        assert!(errors.is_empty(), "{errors:?}");
+        let syntax = syntax.unwrap();
+        assert_eq!(names(&syntax.attrs), [("crate_attr", _AttrStyle::Inner)]);
+        let item = syntax.items[0].inner().unwrap();
+        assert_eq!(names(&item.attrs), [("test", _AttrStyle::Outer)]);
+        let _ItemKind::Func(func) = item.kind.inner().unwrap() else {
+            panic!("{item:?}")
+        };
+        let variants = func.inner().unwrap().variants.inner().unwrap();
+        let block = variants.0[0]
+            .inner()
+            .unwrap()
+            .block
+            .as_ref()
+            .unwrap()
+            .inner()
+            .unwrap();
+        assert_eq!(names(&block.attrs), [("inline", _AttrStyle::Inner)]);
+        assert_eq!(
+            names(&block.stmts[0].inner().unwrap().attrs),
+            [("cold", _AttrStyle::Outer)]
+        );
+    }
+
+    #[test]
+    fn file_with_only_inner_attributes() {
+        let (syntax, errors) = parse("#![a] #![b(c = 1)]");
+        assert!(errors.is_empty(), "{errors:?}");
+        let syntax = syntax.unwrap();
+        assert_eq!(
+            names(&syntax.attrs),
+            [("a", _AttrStyle::Inner), ("b", _AttrStyle::Inner)]
+        );
+        assert!(syntax.items.is_empty());
+    }
+
+    #[test]
+    fn inner_attributes_after_items_are_rejected() {
+        let (_, errors) = parse("fn f() {} #![late]");
+        assert!(!errors.is_empty());
+        let (_, errors) = parse("fn f() { g(); #![late] }");
+        assert!(!errors.is_empty());
+    }
+}
@@ -17,47 +17,241 @@
 use crate::*;
 
 node! {
-    #[doc = "Define a sequence of statements"]
-    Block<'i>(SVec<Stmt<'i>>)
+    #[doc = "Define a sequence of statements, its inner attributes apply to the item that owns it"]
+    Block {
+        attrs: Attributes,
+        stmts: SVec<Stmt>
+    } {
+        recursive(|block| {
+            let expr = recursive(|expr| {
+                let ty = recursive(|ty| types(expr.clone(), ty));
+                expressions(expr, ty, block.clone())
+            });
+            let ty = recursive(|ty| types(expr.clone(), ty));
+            let item = items(expr.clone(), ty.clone(), block.clone());
+            blocks(expr, ty, block, item)
+        })
+    }
 }
 
 node! {
-    #[doc = "Define all the posible closures a function may have"]
-    Variants<'i>(SVec<Closure<'i>>)
+    #[doc = "Define all the posible closures a function may have, several of them are written between braces"]
+    Variants(SVec<Closure>) {
+        variants(_type(), _block())
+    }
 }
 
 node! {
     #[doc = "Define a closure which has a signature and an (optional in traits) block"]
-    Closure<'i> {
-        sig: Signature<'i>,
-        block: Option<Block<'i>>,
+    Closure {
+        sig: Signature,
+        block: Option<Block>,
+    } {
+        closure(_type(), _block())
     }
 }
 
 node! {
     #[doc = "Define a closure signature which has generics, parameters and a return type"]
-    Signature<'i> {
-        generics: Generics<'i>,
-        params: Parameters<'i>,
-        expect: Type<'i>
+    Signature {
+        generics: Generics,
+        params: Parameters,
+        expect: Type
     }
 }
 
 node! {
-    #[doc = "Define a named function"]
-    FuncDef<'i> {
+    #[doc = "Define a named function, `fn name += ...` appends its variants to a function of another implementation"]
+    FuncDef {
         visible: Visibility,
-        name: Ident<'i>,
-        variants: Variants<'i>
+        name: Ident,
+        extend: Extend,
+        variants: Variants
+    } {
+        func_def(_type(), _block())
     }
 }
 
 node! {
     #[doc = "Define parameters which is a list of types"]
-    Parameters<'i>(SVec<Type<'i>>)
+    Parameters(SVec<Param>)
+}
+
+node! {
+    #[doc = "Define a parameter which is a type, named in definitions, along with its attributes"]
+    Param {
+        attrs: Attributes,
+        name: Option<Ident>,
+        ty: Type
+    }
 }
 
 node! {
     #[doc = "Define arguments which is a list of expressions"]
-    Args<'i>(SVec<Expr<'i>>)
+    Args(SVec<Expr>)
+}
+
+/// Parses blocks, whose statements end with a semicolon unless they are the last one or end with a block
+pub(crate) fn blocks<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+    item: impl NodeParser<'i, Item>,
+) -> impl NodeParser<'i, Block> {
+    attributes(_AttrStyle::Inner)
+        .then(
+            statements(expr, ty, block, item)
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .delimited_by(just(OP_BRACE), just(CLS_BRACE))
+        .map_with_span(|(attrs, stmts), s| {
+            Node::new(
+                _Block {
+                    attrs,
+                    stmts: stmts.into(),
+                },
+                s,
+            )
+        })
+        .recover_with(via_parser(skip_braces(Node::skipped)))
+}
+
+/// Parses a single variant or several of them between braces
+pub(crate) fn variants<'i>(
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, Variants> {
+    let closure = closure(ty, block);
+    choice((
+        closure
+            .clone()
+            .repeated()
+            .at_least(1)
+            .collect::<Vec<_>>()
+            .delimited_by(just(OP_BRACE), just(CLS_BRACE)),
+        closure.map(|closure| vec![closure]),
+    ))
+    .map_with_span(|closures, s| Node::new(_Variants(closures.into()), s))
+}
+
+/// Parses a signature followed by its block, or by a semicolon when it has none
+pub(crate) fn closure<'i>(
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, Closure> {
+    signature(generic_params(), ty)
+        .then(choice((block.map(Some), just(SEMICOLON).to(None))))
+        .map_with_span(|(sig, block), s| Node::new(_Closure { sig, block }, s))
+}
+
+/// Parses named functions
+pub(crate) fn func_def<'i>(
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, FuncDef> {
+    _visibility()
+        .then_ignore(just(FN))
+        .then(_ident())
+        .then(_extend())
+        .then(variants(ty, block))
+        .map_with_span(|(((visible, name), extend), variants), s| {
+            Node::new(
+                _FuncDef {
+                    visible,
+                    name,
+                    extend,
+                    variants,
+                },
+                s,
+            )
+        })
+        .boxed()
+}
+
+/// Parses the parameters and the return type of a signature, which returns nothing without an arrow
+pub(crate) fn signature<'i>(
+    generics: impl NodeParser<'i, Generics>,
+    ty: impl NodeParser<'i, Type>,
+) -> impl NodeParser<'i, Signature> {
+    // `self`, `&self` and `&mut self` are named `self` and take `Self` as their type
+    let receiver = just(AMP)
+        .ignore_then(_lifetime().or_not())
+        .then(_mutable())
+        .or_not()
+        .then(just(SELF_PARAM).map_with_span(|_, s| s))
+        .map_with_span(|(reference, s), span| {
+            let ty = Node::new(_Type::LocalSelf, s);
+            let ty = match reference {
+                Some((lifetime, mutable)) => {
+                    let inner = Span::from_combination(mutable.span(), s);
+                    let ty = Node::new(_MutTy { mutable, ty }, inner);
+                    Node::new(_Type::Ref(lifetime, ty), span)
+                }
+                None => ty,
+            };
+            (Node::new(_Ident(sym::SELF), s), ty)
+        });
+    let param = choice((
+        receiver.map(|(name, ty)| (Some(name), ty)),
+        _ident().then_ignore(just(COLON)).or_not().then(ty.clone()),
+    ));
+    let params = _attributes()
+        .then(param)
+        .map_with_span(|(attrs, (name, ty)), s| Node::new(_Param { attrs, name, ty }, s))
+        .separated_by(just(COMMA))
+        .allow_trailing()
+        .collect::<Vec<_>>()
+        .delimited_by(just(OP_PAREN), just(CLS_PAREN))
+        .map_with_span(|params, s| Node::new(_Parameters(params.into()), s));
+    let expect = choice((
+        just(ARROW).ignore_then(ty),
+        empty().map_with_span(|_, s| Node::new(_Type::Null, s)),
+    ));
+    generics
+        .then(params)
+        .then(expect)
+        .map_with_span(|((generics, params), expect), s| {
+            Node::new(
+                _Signature {
+                    generics,
+                    params,
+                    expect,
+                },
+                s,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    #[test]
+    fn variants_of_a_function() {
+        assert_eq!(sexp(_item(), "fn f(a: u8) {}"), "(fn f (a: u8) () {})");
+        assert_eq!(
+            sexp(_item(), "fn f { (a: u8) -> u8 { a } <T>(b: T, u8); }"),
+            "(fn f (a: u8) u8 {a} | <T> (b: T, u8) ();)"
+        );
+        assert_eq!(
+            sexp(_item(), "pub fn f += { (self) {} }"),
+            "(fn pub f += (self: Self) () {})"
+        );
+        assert!(rejects(_item(), "fn f {}"));
+        assert!(rejects(_item(), "fn f += ;"));
+    }
+
+    #[test]
+    fn each_variant_has_its_span() {
+        let source = "fn f { (a: u8) {} (b: char); }";
+        let func = parse_all(_funcdef(), source);
+        let variants = func.inner().unwrap().variants.inner().unwrap();
+        let spans = variants
+            .0
+            .iter()
+            .map(|variant| &source[variant.span().range()])
+            .collect::<Vec<_>>();
+        assert_eq!(spans, ["(a: u8) {}", "(b: char);"]);
+    }
 }
@@ -18,15 +18,45 @@ use crate::*;
 
 node! {
     #[doc = "Define an anonymous constant"]
-    Const<'i>(Expr<'i>)
+    Const(Expr)
 }
 
 node! {
     #[doc = "Define a constant"]
-    ConstDef<'i> {
+    ConstDef {
         vis: Visibility,
-        name: Ident<'i>,
-        ty: Type<'i>,
-        value: Const<'i>,
+        name: Ident,
+        ty: Type,
+        value: Const,
+    } {
+        const_def(_expr(), _type())
     }
 }
+
+/// Parses constants
+pub(crate) fn const_def<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+) -> impl NodeParser<'i, ConstDef> {
+    _visibility()
+        .then_ignore(just(CONST))
+        .then(_ident())
+        .then_ignore(just(COLON))
+        .then(ty.then(assign()).validate(|(ty, split), _, emitter| {
+            check_assign(&ty, split, emitter);
+            ty
+        }))
+        .then(expr.map_with_span(|expr, s| Node::new(_Const(expr), s)))
+        .then_ignore(just(SEMICOLON))
+        .map_with_span(|(((vis, name), ty), value), s| {
+            Node::new(
+                _ConstDef {
+                    vis,
+                    name,
+                    ty,
+                    value,
+                },
+                s,
+            )
+        })
+}
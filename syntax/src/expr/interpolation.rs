@@ -0,0 +1,30 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::*;
+
+node! {
+    #[doc = "Define format strings, which interpolate expressions into their text"]
+    Format(SVec<FormatSegment>)
+}
+
+node! {
+    #[doc = "Define the segments of format strings, expressions may be followed by a format spec"]
+    case FormatSegment {
+        Text(Symbol),
+        Expr(Expr, Option<Symbol>),
+    }
+}
@@ -15,90 +15,740 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod binary;
+pub mod interpolation;
 pub mod range;
 pub mod unary;
 
 use crate::*;
 
+use std::iter::Peekable;
+
 use binary::*;
+use interpolation::*;
 use range::*;
 use unary::*;
 
 node! {
     #[doc = "Define expressions"]
-    case Expr<'i> {
-        Path(Path<'i>),
+    case Expr {
+        Path(Path),
         Underscore,
-        Parenthesized(Expr<'i>),
-        Refer(Expr<'i>),
-        Unary(Unary, Expr<'i>),
+        Parenthesized(Expr),
+        Refer(MutExpr),
+        Unary(Unary, Expr),
         Binary {
             op: Binary,
-            lhs: Expr<'i>,
-            rhs: Expr<'i>,
+            lhs: Expr,
+            rhs: Expr,
         },
-        Literal(Literal<'i>),
-        Struct(Path<'i>, StructTy<'i>),
-        Block(Block<'i>),
-        Array(SVec<Expr<'i>>),
-        Tup(TupExpr<'i>),
-        Closure(MoveScope ,Closure<'i>),
-        Range(Range<'i>),
-        Assign(DerefValue, Ident<'i>, Option<Binary>, Expr<'i>),
-        Field(Expr<'i>, Ident<'i>),
-        Call(Expr<'i>, Args<'i>),
+        Literal(Literal),
+        Format(Format),
+        Struct(Path, StructTy),
+        Block(Block),
+        Array(SVec<Expr>),
+        Tup(TupExpr),
+        Closure(MoveScope ,Closure),
+        Range(Range),
+        Assign(DerefValue, Ident, Option<Binary>, Expr),
+        Field(Expr, Ident),
+        Call(Expr, Args),
         Method {
-            def: Expr<'i>,
-            method: Path<'i>,
-            args: Args<'i>,
+            def: Expr,
+            method: Path,
+            args: Args,
         },
-        Index(Expr<'i>, Expr<'i>),
-        Cast(Expr<'i>, Type<'i>),
+        Index(Expr, Expr),
+        Cast(Expr, Type),
         Cond {
-            check: Expr<'i>,
-            then: Expr<'i>,
-            not: Option<Expr<'i>>,
+            check: Expr,
+            then: Expr,
+            not: Option<Expr>,
         },
-        Loop(Expr<'i>),
+        Loop(Expr),
         ForLoop {
-            item: Ident<'i>,
-            iter: Expr<'i>,
-            block: Expr<'i>,
+            item: Ident,
+            iter: Expr,
+            block: Expr,
         },
         While {
-            check: Expr<'i>,
-            block: Expr<'i>,
+            check: Expr,
+            block: Expr,
         },
         Continue,
         Break,
+    } {
+        recursive(|expr| {
+            let ty = recursive(|ty| types(expr.clone(), ty));
+            expressions(expr, ty, _block())
+        })
     }
 }
 
 node! {
     #[doc = "Define a list of expressions"]
-    TupExpr<'i>(SVec<Expr<'i>>)
+    TupExpr(SVec<Expr>)
 }
 
 node! {
     #[doc = "Define struct constructor"]
-    case StructExpr<'i> {
-        Tup(TupExpr<'i>),
-        Field(SVec<FieldExpr<'i>>)
+    case StructExpr {
+        Tup(TupExpr),
+        Field(SVec<FieldExpr>)
     }
 }
 
 node! {
     #[doc = "Define constructor of fields"]
-    FieldExpr<'i> {
-        path: Path<'i>,
-        expr: Expr<'i>
+    FieldExpr {
+        path: Path,
+        expr: Expr
     }
 }
 
 node! {
     #[doc = "Defines if the expression is mutable"]
-    MutExpr<'i> {
+    MutExpr {
         mutable: Mutable,
-        expr: Expr<'i>
+        expr: Expr
+    }
+}
+
+/// Operations written after an expression
+enum Postfix {
+    Method(Path, Args),
+    Field(Ident),
+    Call(Args),
+    Index(Expr),
+}
+
+/// Parses expressions, from the loosest to the tightest binding:
+///
+/// | Expressions                                          | Associativity |
+/// |------------------------------------------------------|---------------|
+/// | `=` `+=` `-=` `*=` `/=` `%=` `**=` `<<=` `>>=` `^=`  | right         |
+/// | `..` `..=`                                           | none          |
+/// | `\|\|`                                               | left          |
+/// | `&&`                                                 | left          |
+/// | `==` `!=` `>` `<` `>=` `<=`                          | left          |
+/// | `^`                                                  | left          |
+/// | `<<` `>>`                                            | left          |
+/// | `+` `-`                                              | left          |
+/// | `*` `/` `%`                                          | left          |
+/// | `as`                                                 | left          |
+/// | unary `-` `!` `*` `&` `&mut`                         | prefix        |
+/// | `**`                                                 | right         |
+/// | method calls, fields, calls and indexing             | postfix       |
+pub(crate) fn expressions<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, Expr> {
+    let head = heads(expr.clone(), ty.clone(), block.clone());
+    operators(expr, ty, block, head, true)
+}
+
+/// Parses the expressions heading the block of `if`, `while` and `for`,
+/// where a brace after `..` opens that block instead of ending the range
+pub(crate) fn heads<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, Expr> {
+    recursive(|head| operators(expr, ty, block, head, false))
+}
+
+fn operators<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+    head: impl NodeParser<'i, Expr>,
+    block_end: bool,
+) -> impl NodeParser<'i, Expr> {
+    let list = |open, close| {
+        expr.clone()
+            .separated_by(just(COMMA))
+            .allow_trailing()
+            .collect::<Vec<_>>()
+            .delimited_by(just(open), just(close))
+    };
+    let args = list(OP_PAREN, CLS_PAREN).map_with_span(|args, s| Node::new(_Args(args.into()), s));
+
+    let format = select! {
+        TokenKind::Format(FormatStr(parts)) = s => (parts, s),
+    }
+    .validate({
+        let expr = expr.clone().then_ignore(end());
+        move |(parts, s): (Vec<FormatPart<'i>>, Span), _, emitter| {
+            let segments = parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    FormatPart::Text(text, span) => {
+                        Some(Node::new(_FormatSegment::Text(text), span))
+                    }
+                    // The lexer already reports the braces enclosing no expression
+                    FormatPart::Expr(tokens, ..) if tokens.tokens().is_empty() => None,
+                    FormatPart::Expr(tokens, spec, span) => {
+                        let (expr, errors) = expr.parse(tokens).into_output_errors();
+                        errors.into_iter().for_each(|error| emitter.emit(error));
+                        expr.map(|expr| Node::new(_FormatSegment::Expr(expr, spec), span))
+                    }
+                })
+                .collect::<Vec<_>>();
+            Node::new(_Expr::Format(Node::new(_Format(segments.into()), s)), s)
+        }
+    });
+
+    // A single expression is only a tuple when a comma follows it
+    let tuple = expr
+        .clone()
+        .separated_by(just(COMMA))
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .then(just(COMMA).or_not())
+        .or_not()
+        .delimited_by(just(OP_PAREN), just(CLS_PAREN))
+        .map_with_span(|exprs, s| match exprs {
+            Some((mut exprs, None)) if exprs.len() == 1 => {
+                Node::new(_Expr::Parenthesized(exprs.pop().unwrap()), s)
+            }
+            exprs => {
+                let exprs = exprs.map_or_else(Vec::new, |(exprs, _)| exprs);
+                Node::new(_Expr::Tup(Node::new(_TupExpr(exprs.into()), s)), s)
+            }
+        });
+
+    let closure = _movescope()
+        .then_ignore(just(FN))
+        .then(
+            signature(generic_params(), ty.clone())
+                .then(block.clone())
+                .map_with_span(|(sig, block), s| {
+                    Node::new(
+                        _Closure {
+                            sig,
+                            block: Some(block),
+                        },
+                        s,
+                    )
+                }),
+        )
+        .map_with_span(|(scope, closure), s| Node::new(_Expr::Closure(scope, closure), s));
+
+    let primary = choice((
+        block_like(head, block),
+        closure,
+        _literal().map_with_span(|literal, s| Node::new(_Expr::Literal(literal), s)),
+        format,
+        path(ty.clone(), true).map_with_span(|path, s| Node::new(_Expr::Path(path), s)),
+        just(UNDERLINE).map_with_span(|_, s| Node::new(_Expr::Underscore, s)),
+        tuple,
+        list(OP_SQ_BRACKET, CLS_SQ_BRACKET)
+            .map_with_span(|exprs, s| Node::new(_Expr::Array(exprs.into()), s)),
+        just(CONTINUE).map_with_span(|_, s| Node::new(_Expr::Continue, s)),
+        just(BREAK).map_with_span(|_, s| Node::new(_Expr::Break, s)),
+    ))
+    .boxed();
+
+    let method = _ident()
+        .then(
+            just(DOUBLE_COLON)
+                .ignore_then(generics(ty.clone()))
+                .or_not(),
+        )
+        .map_with_span(|(ident, generics), s| {
+            let generics = generics.unwrap_or_else(|| Generics::empty(&ident));
+            Node::new(
+                _Path {
+                    ident,
+                    generics,
+                    subpath: None,
+                },
+                s,
+            )
+        });
+    let postfix = primary
+        .foldl(
+            choice((
+                just(DOT)
+                    .ignore_then(method)
+                    .then(args.clone())
+                    .map(|(method, args)| Postfix::Method(method, args)),
+                just(DOT).ignore_then(_ident()).map(Postfix::Field),
+                args.map(Postfix::Call),
+                expr.clone()
+                    .delimited_by(just(OP_SQ_BRACKET), just(CLS_SQ_BRACKET))
+                    .map(Postfix::Index),
+            ))
+            .map_with_span(|op, s: Span| (op, s))
+            .repeated(),
+            |def, (op, s)| {
+                let span = Span::from_combination(def.span(), s);
+                Node::new(
+                    match op {
+                        Postfix::Method(method, args) => _Expr::Method { def, method, args },
+                        Postfix::Field(ident) => _Expr::Field(def, ident),
+                        Postfix::Call(args) => _Expr::Call(def, args),
+                        Postfix::Index(index) => _Expr::Index(def, index),
+                    },
+                    span,
+                )
+            },
+        )
+        .boxed();
+
+    let unary = recursive(|unary| {
+        let operator = choice((
+            just(LINE).to(_Unary::Negative),
+            just(EXCLM).to(_Unary::Negation),
+            just(STAR).to(_Unary::Deref),
+            just(STAR_STAR).to(_Unary::Deref),
+        ))
+        .map_with_span(|op, s: Span| (op, s))
+        .then(unary.clone())
+        .map(|((op, s), expr): (_, Expr)| {
+            let prefix = |op: _Unary, s: Span, expr: Expr| {
+                let span = Span::from_combination(s, expr.span());
+                Node::new(_Expr::Unary(Node::new(op, s), expr), span)
+            };
+            match *s.length() {
+                // `**` is lexed as a single token, so it is split into two dereferences
+                2 => {
+                    let expr = prefix(op.clone(), Span::new(s.offset() + 1, 1), expr);
+                    prefix(op, Span::new(*s.offset(), 1), expr)
+                }
+                _ => prefix(op, s, expr),
+            }
+        });
+        let refer = choice((just(AMP).to(false), just(AND).to(true)))
+            .map_with_span(|double, s: Span| (double, s))
+            .then(
+                _mutable()
+                    .then(unary.clone())
+                    .map_with_span(|(mutable, expr), s| Node::new(_MutExpr { mutable, expr }, s)),
+            )
+            .map_with_span(|((double, amp), expr), s: Span| {
+                let inner = Span::new(amp.offset() + double as usize, s.length() - double as usize);
+                let refer = Node::new(_Expr::Refer(expr), inner);
+                match double {
+                    // `&&` is lexed as a single token, so it is split into two references
+                    true => {
+                        let mutable = Node::new(_Mutable(false), Span::new(*amp.offset(), 0));
+                        let expr = Node::new(
+                            _MutExpr {
+                                mutable,
+                                expr: refer,
+                            },
+                            inner,
+                        );
+                        Node::new(_Expr::Refer(expr), s)
+                    }
+                    false => refer,
+                }
+            });
+        let prefix = choice((operator, refer));
+        let power = postfix
+            .then(
+                just(STAR_STAR)
+                    .map_with_span(|_, s| Node::new(_Binary::Exp, s))
+                    .then(unary)
+                    .or_not(),
+            )
+            .map(|(lhs, rhs)| match rhs {
+                Some((op, rhs)) => binary(op, lhs, rhs),
+                None => lhs,
+            });
+        prefix.or(power)
+    });
+
+    let cast = unary
+        .foldl(just(AS).ignore_then(ty).repeated(), |expr, ty| {
+            let span = Span::from_combination(expr.span(), ty.span());
+            Node::new(_Expr::Cast(expr, ty), span)
+        })
+        .boxed();
+
+    let operator = choice((
+        just(STAR).to(_Binary::Mul),
+        just(SLASH).to(_Binary::Div),
+        just(PERC).to(_Binary::Remainder),
+        just(PLUS).to(_Binary::Add),
+        just(LINE).to(_Binary::Sub),
+        just(L_SHT).to(_Binary::LSht),
+        just(R_SHT).to(_Binary::RSht),
+        just(CARET).to(_Binary::XOr),
+        just(EQ_EQ).to(_Binary::Eq),
+        just(NOT_EQ).to(_Binary::NotEq),
+        just(CLS_CHEVRON).to(_Binary::Gr),
+        just(OP_CHEVRON).to(_Binary::Less),
+        just(GR_EQ).to(_Binary::GrEq),
+        just(LESS_EQ).to(_Binary::LessEq),
+        just(AND).to(_Binary::And),
+        just(OR).to(_Binary::Or),
+    ))
+    .map_with_span(Node::new);
+    let binary = cast
+        .clone()
+        .then(operator.then(cast).repeated().collect::<Vec<_>>())
+        .map(|(lhs, rest)| climb(lhs, &mut rest.into_iter().peekable(), 0))
+        .boxed();
+
+    let bound = |expr: Expr| {
+        let span = expr.span();
+        Node::new(_Const(expr), span)
+    };
+    let open_end = match block_end {
+        true => binary.clone(),
+        false => just(OP_BRACE).not().ignore_then(binary.clone()).boxed(),
+    };
+    let end = choice((
+        just(DOT_DOT_EQ)
+            .ignore_then(binary.clone())
+            .map(|end| (true, Some(end))),
+        just(DOT_DOT)
+            .ignore_then(open_end.or_not())
+            .map(|end| (false, end)),
+    ));
+    let range = choice((
+        end.clone().map_with_span(move |end, s| {
+            let range = match end {
+                (true, Some(end)) => _Range::ToInclusive(bound(end)),
+                (_, Some(end)) => _Range::To(bound(end)),
+                (_, None) => _Range::Full,
+            };
+            Node::new(_Expr::Range(Node::new(range, s)), s)
+        }),
+        binary
+            .then(end.or_not())
+            .map_with_span(move |(start, end), s| {
+                let range = match end {
+                    Some((true, Some(end))) => _Range::Inclusive(bound(start), bound(end)),
+                    Some((_, Some(end))) => _Range::Bounded(bound(start), bound(end)),
+                    Some((_, None)) => _Range::From(bound(start)),
+                    None => return start,
+                };
+                Node::new(_Expr::Range(Node::new(range, s)), s)
+            }),
+    ));
+
+    let assign = just(STAR)
+        .or_not()
+        .map_with_span(|deref, s| Node::new(_DerefValue(deref.is_some()), s))
+        .then(_ident())
+        .then(
+            choice((
+                just(EQ).to(None),
+                just(PLUS_EQ).to(Some(_Binary::Add)),
+                just(LINE_EQ).to(Some(_Binary::Sub)),
+                just(STAR_EQ).to(Some(_Binary::Mul)),
+                just(SLASH_EQ).to(Some(_Binary::Div)),
+                just(PERC_EQ).to(Some(_Binary::Remainder)),
+                just(STAR_STAR_EQ).to(Some(_Binary::Exp)),
+                just(L_SHT_EQ).to(Some(_Binary::LSht)),
+                just(R_SHT_EQ).to(Some(_Binary::RSht)),
+                just(CARET_EQ).to(Some(_Binary::XOr)),
+            ))
+            .map_with_span(|op, s| op.map(|op| Node::new(op, s))),
+        )
+        .then(expr)
+        .map_with_span(|(((deref, ident), op), expr), s| {
+            Node::new(_Expr::Assign(deref, ident, op, expr), s)
+        });
+
+    choice((assign, range)).boxed()
+}
+
+/// Parses expressions that end with a block, as statements they need no semicolon
+pub(crate) fn block_like<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, Expr> {
+    let block = block.map_with_span(|block, s| Node::new(_Expr::Block(block), s));
+    let cond = recursive(|cond| {
+        just(IF)
+            .ignore_then(expr.clone())
+            .then(block.clone())
+            .then(
+                just(ELSE)
+                    .ignore_then(choice((cond, block.clone())))
+                    .or_not(),
+            )
+            .map_with_span(|((check, then), not), s| Node::new(_Expr::Cond { check, then, not }, s))
+    });
+    choice((
+        block.clone(),
+        cond,
+        just(LOOP)
+            .ignore_then(block.clone())
+            .map_with_span(|block, s| Node::new(_Expr::Loop(block), s)),
+        just(WHILE)
+            .ignore_then(expr.clone())
+            .then(block.clone())
+            .map_with_span(|(check, block), s| Node::new(_Expr::While { check, block }, s)),
+        just(FOR)
+            .ignore_then(_ident())
+            .then_ignore(just(IN))
+            .then(expr)
+            .then(block)
+            .map_with_span(|((item, iter), block), s| {
+                Node::new(_Expr::ForLoop { item, iter, block }, s)
+            }),
+    ))
+    .boxed()
+}
+
+/// Binding power of binary operators, higher powers bind tighter
+fn binding_power(op: &Binary) -> u8 {
+    match op.inner() {
+        Some(_Binary::Exp) => 8,
+        Some(_Binary::Mul | _Binary::Div | _Binary::Remainder) => 7,
+        Some(_Binary::Add | _Binary::Sub) => 6,
+        Some(_Binary::LSht | _Binary::RSht) => 5,
+        Some(_Binary::XOr) => 4,
+        Some(
+            _Binary::Eq
+            | _Binary::NotEq
+            | _Binary::Gr
+            | _Binary::Less
+            | _Binary::GrEq
+            | _Binary::LessEq,
+        ) => 3,
+        Some(_Binary::And) => 2,
+        Some(_Binary::Or) => 1,
+        None => 0,
+    }
+}
+
+/// Folds a sequence of left associative binary operations by precedence climbing
+fn climb(
+    mut lhs: Expr,
+    rest: &mut Peekable<impl Iterator<Item = (Binary, Expr)>>,
+    min: u8,
+) -> Expr {
+    while let Some(power) = rest
+        .peek()
+        .map(|(op, _)| binding_power(op))
+        .filter(|power| *power >= min)
+    {
+        let (op, rhs) = rest.next().unwrap();
+        let rhs = climb(rhs, rest, power + 1);
+        lhs = binary(op, lhs, rhs);
+    }
+    lhs
+}
+
+/// Joins two expressions with a binary operator
+fn binary(op: Binary, lhs: Expr, rhs: Expr) -> Expr {
+    let span = Span::from_combination(lhs.span(), rhs.span());
+    Node::new(_Expr::Binary { op, lhs, rhs }, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    #[test]
+    fn receivers_and_the_implemented_type() {
+        assert_eq!(sexp(_expr(), "self"), "self");
+        assert_eq!(sexp(_expr(), "self.x"), "(. self x)");
+        assert_eq!(sexp(_expr(), "Self::new()"), "(call Self::new)");
+        assert_eq!(
+            sexp(_expr(), "Self::new::<u8>(self.len())"),
+            "(call Self::new<u8> (method self len))"
+        );
+        assert_eq!(
+            sexp(_item(), "impl A { fn f(&self) -> u8 { self.x } }"),
+            "(impl A (fn f (self: (& Self)) u8 {(. self x)}))"
+        );
+        assert_eq!(
+            sexp(_item(), "fn f() -> Self { Self::new() }"),
+            "(fn f () Self {(call Self::new)})"
+        );
+    }
+
+    #[test]
+    fn parentheses_and_tuples() {
+        assert_eq!(sexp(_expr(), "()"), "(tup)");
+        assert_eq!(sexp(_expr(), "(a)"), "(paren a)");
+        assert_eq!(sexp(_expr(), "(a,)"), "(tup a)");
+        assert_eq!(sexp(_expr(), "(a, b)"), "(tup a b)");
+        assert_eq!(
+            sexp(_expr(), "(a, (b,), ((c)))"),
+            "(tup a (tup b) (paren (paren c)))"
+        );
+        assert_eq!(
+            sexp(_expr(), "((a + b) * c)"),
+            "(paren (* (paren (+ a b)) c))"
+        );
+    }
+
+    #[test]
+    fn deep_parentheses_parse_in_linear_time() {
+        let source = format!("{}1{}", "(".repeat(64), ")".repeat(64));
+        let start = std::time::Instant::now();
+        assert!(sexp(_expr(), &source).ends_with(&format!("1{}", ")".repeat(64))));
+        assert!(start.elapsed().as_secs() < 5);
+    }
+
+    #[test]
+    fn double_ampersand_is_two_references() {
+        assert_eq!(sexp(_expr(), "&a"), "(& a)");
+        assert_eq!(sexp(_expr(), "&&a"), "(& (& a))");
+        assert_eq!(sexp(_expr(), "&&&a"), "(& (& (& a)))");
+        assert_eq!(sexp(_expr(), "a && &&b"), "(&& a (& (& b)))");
+        let expr = parse_all(_expr(), "&&a");
+        let _Expr::Refer(inner) = expr.inner().unwrap() else {
+            panic!()
+        };
+        assert_eq!((*expr.span().offset(), *expr.span().length()), (0, 3));
+        assert_eq!((*inner.span().offset(), *inner.span().length()), (1, 2));
+    }
+
+    #[test]
+    fn mutable_references() {
+        assert_eq!(sexp(_expr(), "&mut a"), "(& mut a)");
+        assert_eq!(sexp(_expr(), "&mut a.b[0]"), "(& mut (index (. a b) 0))");
+        assert_eq!(sexp(_expr(), "&&mut a"), "(& (& mut a))");
+        assert_eq!(sexp(_expr(), "&mut &a"), "(& mut (& a))");
+        assert_eq!(sexp(_expr(), "f(&mut a, &b)"), "(call f (& mut a) (& b))");
+        assert!(rejects(_expr(), "&mut"));
+        let expr = parse_all(_expr(), "&mut a");
+        let _Expr::Refer(inner) = expr.inner().unwrap() else {
+            panic!()
+        };
+        let inner = inner.inner().unwrap();
+        assert!(inner.mutable.inner().unwrap().0);
+        assert_eq!(
+            (
+                *inner.mutable.span().offset(),
+                *inner.mutable.span().length()
+            ),
+            (1, 3)
+        );
+    }
+
+    #[test]
+    fn double_star_is_two_dereferences() {
+        assert_eq!(sexp(_expr(), "**a"), "(* (* a))");
+        assert_eq!(sexp(_expr(), "***a.b"), "(* (* (* (. a b))))");
+        assert_eq!(sexp(_expr(), "a ** **b"), "(** a (* (* b)))");
+        assert_eq!(sexp(_expr(), "**a ** b"), "(* (* (** a b)))");
+        let expr = parse_all(_expr(), "**a");
+        let _Expr::Unary(op, inner) = expr.inner().unwrap() else {
+            panic!()
+        };
+        assert_eq!((*op.span().offset(), *op.span().length()), (0, 1));
+        assert_eq!((*inner.span().offset(), *inner.span().length()), (1, 2));
+    }
+
+    #[test]
+    fn open_ranges_before_a_block() {
+        assert_eq!(sexp(_expr(), "for i in 0.. { i }"), "(for i (.. 0 _) {i})");
+        assert_eq!(sexp(_expr(), "for i in .. {}"), "(for i (.. _ _) {})");
+        assert_eq!(sexp(_expr(), "for i in 0..n { i }"), "(for i (.. 0 n) {i})");
+        assert_eq!(sexp(_expr(), "while x < y {}"), "(while (< x y) {})");
+        assert_eq!(sexp(_expr(), "if a.. {} else {}"), "(if (.. a _) {} {})");
+        assert_eq!(sexp(_expr(), "0..{ n }"), "(.. 0 {n})");
+        assert_eq!(
+            sexp(_expr(), "for i in (0..{ n }) {}"),
+            "(for i (paren (.. 0 {n})) {})"
+        );
+        assert_eq!(sexp(_stmt(), "for i in 0.. {}"), "(for i (.. 0 _) {})");
+    }
+
+    #[test]
+    fn precedence_from_loosest_to_tightest() {
+        assert_eq!(
+            sexp(_expr(), "a || b && c == d ^ e << f + g * h as u8"),
+            "(|| a (&& b (== c (^ d (<< e (+ f (* g (as h u8))))))))"
+        );
+        assert_eq!(
+            sexp(_expr(), "a as u8 * b + c << d ^ e == f && g || h"),
+            "(|| (&& (== (^ (<< (+ (* (as a u8) b) c) d) e) f) g) h)"
+        );
+        assert_eq!(sexp(_expr(), "a + b .. c * d"), "(.. (+ a b) (* c d))");
+        assert_eq!(sexp(_expr(), "x = a .. b"), "(= x (.. a b))");
+        assert_eq!(sexp(_expr(), "-a * b"), "(* (- a) b)");
+        assert_eq!(sexp(_expr(), "!a == *b"), "(== (! a) (* b))");
+        assert_eq!(sexp(_expr(), "-a as i8"), "(as (- a) i8)");
+    }
+
+    #[test]
+    fn left_associative_operators() {
+        assert_eq!(sexp(_expr(), "a - b - c"), "(- (- a b) c)");
+        assert_eq!(sexp(_expr(), "a / b * c % d"), "(% (* (/ a b) c) d)");
+        assert_eq!(sexp(_expr(), "a << b >> c"), "(>> (<< a b) c)");
+        assert_eq!(sexp(_expr(), "a < b >= c"), "(>= (< a b) c)");
+        assert_eq!(sexp(_expr(), "a || b || c"), "(|| (|| a b) c)");
+        assert_eq!(sexp(_expr(), "a as u8 as u16"), "(as (as a u8) u16)");
+    }
+
+    #[test]
+    fn exponent_is_right_associative_and_binds_tighter_than_prefixes() {
+        assert_eq!(sexp(_expr(), "a ** b ** c"), "(** a (** b c))");
+        assert_eq!(sexp(_expr(), "-a ** b"), "(- (** a b))");
+        assert_eq!(sexp(_expr(), "a ** -b"), "(** a (- b))");
+        assert_eq!(sexp(_expr(), "a * b ** c * d"), "(* (* a (** b c)) d)");
+        assert_eq!(sexp(_expr(), "a.b ** c()"), "(** (. a b) (call c))");
+    }
+
+    #[test]
+    fn assignments_are_right_associative() {
+        assert_eq!(sexp(_expr(), "a = b = c"), "(= a (= b c))");
+        assert_eq!(sexp(_expr(), "*a += b * c"), "(+= *a (* b c))");
+        assert_eq!(sexp(_expr(), "a **= b"), "(**= a b)");
+        assert_eq!(sexp(_expr(), "a >>= b"), "(>>= a b)");
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(sexp(_expr(), ".."), "(.. _ _)");
+        assert_eq!(sexp(_expr(), "a.."), "(.. a _)");
+        assert_eq!(sexp(_expr(), "..b"), "(.. _ b)");
+        assert_eq!(sexp(_expr(), "a..b"), "(.. a b)");
+        assert_eq!(sexp(_expr(), "a..=b"), "(..= a b)");
+        assert_eq!(sexp(_expr(), "..=b"), "(..= _ b)");
+        assert!(rejects(_expr(), "a..="));
+        assert!(rejects(_expr(), "a..b..c"));
+    }
+
+    #[test]
+    fn postfix_chains() {
+        assert_eq!(
+            sexp(_expr(), "a.b.c(d)[e](f)"),
+            "(call (index (method (. a b) c d) e) f)"
+        );
+        assert_eq!(sexp(_expr(), "a.b::<u8>()"), "(method a b<u8>)");
+        assert_eq!(sexp(_expr(), "*a.b[c]"), "(* (index (. a b) c))");
+        assert_eq!(sexp(_expr(), "[a, b,][0]"), "(index [a b] 0)");
+    }
+
+    #[test]
+    fn format_strings() {
+        assert_eq!(sexp(_expr(), "f\"\""), "(f)");
+        assert_eq!(
+            sexp(_expr(), "f\"a {b + 1:>4} {{c}}\""),
+            "(f \"a \" {(+ b 1)} \" {c}\")"
+        );
+        assert_eq!(
+            sexp(_expr(), "f\"{a.b(c)}\".len()"),
+            "(method (f {(method a b c)}) len)"
+        );
+        assert!(matches!(
+            parse_all(_expr(), "f\"{a}\"").inner(),
+            Some(_Expr::Format(_))
+        ));
+        assert!(rejects(_expr(), "f\"{a +}\""));
+    }
+
+    #[test]
+    fn empty_interpolations_are_left_to_the_lexer() {
+        let (tokens, errors) = TokenStream::new("f\"a{}b{c}\"");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        let (expr, errors) = _expr()
+            .then_ignore(end())
+            .parse(tokens)
+            .into_output_errors();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(expr.unwrap().sexp(), "(f \"a\" \"b\" {c})");
     }
 }
@@ -18,17 +18,91 @@ use crate::*;
 
 node! {
     #[doc = "Define implementations"]
-    ImplDef<'i> {
-        spec: Option<Path<'i>>,
-        name: Path<'i>,
-        funcs: SVec<FuncDef<'i>>
+    ImplDef {
+        spec: Option<Path>,
+        name: Path,
+        funcs: SVec<FuncDef>
+    } {
+        impl_def(_type(), _block())
     }
 }
 
 node! {
     #[doc = "Define traits"]
-    TraitDef<'i> {
-        name: Ident<'i>,
-        funcs: SVec<FuncDef<'i>>
+    TraitDef {
+        vis: Visibility,
+        name: Ident,
+        funcs: SVec<FuncDef>
+    } {
+        trait_def(_type(), _block())
+    }
+}
+
+/// Parses implementations, `impl Spec for Name` implements a trait
+pub(crate) fn impl_def<'i>(
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, ImplDef> {
+    just(IMPL)
+        .ignore_then(path(ty.clone(), false))
+        .then(just(FOR).ignore_then(path(ty.clone(), false)).or_not())
+        .then(funcs(ty, block))
+        .map_with_span(|((path, name), funcs), s| {
+            let (spec, name) = match name {
+                Some(name) => (Some(path), name),
+                None => (None, path),
+            };
+            Node::new(_ImplDef { spec, name, funcs }, s)
+        })
+}
+
+/// Parses traits
+pub(crate) fn trait_def<'i>(
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, TraitDef> {
+    _visibility()
+        .then_ignore(just(TRAIT))
+        .then(_ident())
+        .then(funcs(ty, block))
+        .map_with_span(|((vis, name), funcs), s| Node::new(_TraitDef { vis, name, funcs }, s))
+}
+
+/// Parses the functions of implementations and traits
+fn funcs<'i>(
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, SVec<FuncDef>> {
+    func_def(ty, block)
+        .repeated()
+        .collect::<Vec<_>>()
+        .delimited_by(just(OP_BRACE), just(CLS_BRACE))
+        .map(SVec::from)
+        .recover_with(via_parser(skip_braces(|_| SVec::new())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    #[test]
+    fn implementations_and_traits() {
+        assert_eq!(
+            sexp(
+                _item(),
+                "impl A<T> { fn f() {} pub fn g(self) -> u8 { 1 } }"
+            ),
+            "(impl A<T> (fn f () () {}) (fn pub g (self: Self) u8 {1}))"
+        );
+        assert_eq!(
+            sexp(_item(), "impl a::Spec<u8> for B<C<D>> { fn f(); }"),
+            "(impl a::Spec<u8> for B<C<D>> (fn f () ();))"
+        );
+        assert_eq!(
+            sexp(_item(), "trait A { fn f(&self) -> u8; fn g() {} }"),
+            "(trait A (fn f (self: (& Self)) u8;) (fn g () () {}))"
+        );
+        assert!(rejects(_item(), "impl A for {}"));
+        assert!(rejects(_item(), "trait A<T> {}"));
     }
 }
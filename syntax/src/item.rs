@@ -17,38 +17,160 @@
 use crate::*;
 
 node! {
-    #[doc = "Define items"]
-    case Item<'i> {
-        Module(ModuleDef<'i>),
-        Import(ImportDef<'i>),
-        Const(ConstDef<'i>),
-        Type(TypeDef<'i>),
-        Impl(ImplDef<'i>),
-        Trait(TraitDef<'i>),
-        Func(FuncDef<'i>),
+    #[doc = "Define an item along with its attributes"]
+    Item {
+        attrs: Attributes,
+        kind: ItemKind
+    } {
+        items(_expr(), _type(), _block())
+    }
+}
+
+node! {
+    #[doc = "Define kinds of items"]
+    case ItemKind {
+        Module(ModuleDef),
+        Import(ImportDef),
+        Const(ConstDef),
+        Type(TypeDef),
+        Impl(ImplDef),
+        Trait(TraitDef),
+        Func(FuncDef),
+    } {
+        item_kinds(_expr(), _type(), _block())
     }
 }
 
 node! {
     #[doc = "Define module"]
-    ModuleDef<'i> {
+    ModuleDef {
         vis: Visibility,
-        names: Ident<'i>
+        names: Ident
+    } {
+        _visibility()
+            .then_ignore(just(MOD))
+            .then(_ident())
+            .then_ignore(just(SEMICOLON))
+            .map_with_span(|(vis, names), s| Node::new(_ModuleDef { vis, names }, s))
     }
 }
 
 node! {
     #[doc = "Define import"]
-    ImportDef<'i> {
+    ImportDef {
         vis: Visibility,
-        names: SVec<ImportName<'i>>
+        names: SVec<ImportName>
+    } {
+        _visibility()
+            .then_ignore(just(USE))
+            .then(
+                _importname()
+                    .separated_by(just(COMMA))
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(just(SEMICOLON))
+            .map_with_span(|(vis, names), s| {
+                Node::new(
+                    _ImportDef {
+                        vis,
+                        names: names.into(),
+                    },
+                    s,
+                )
+            })
     }
 }
 
 node! {
     #[doc = "Define subimports"]
-    ImportName<'i> {
-        path: Path<'i>,
-        alias: Option<Ident<'i>>
+    ImportName {
+        path: Path,
+        alias: Option<Ident>
+    } {
+        plain_path()
+            .then(just(AS).ignore_then(_ident()).or_not())
+            .map_with_span(|(path, alias), s| Node::new(_ImportName { path, alias }, s))
+    }
+}
+
+/// Parses items along with their attributes
+pub(crate) fn items<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, Item> {
+    _attributes()
+        .then(item_kinds(expr, ty, block))
+        .map_with_span(|(attrs, kind), s| Node::new(_Item { attrs, kind }, s))
+}
+
+/// Parses every kind of item
+pub(crate) fn item_kinds<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+) -> impl NodeParser<'i, ItemKind> {
+    choice((
+        _moduledef().map(_ItemKind::Module),
+        _importdef().map(_ItemKind::Import),
+        const_def(expr, ty.clone()).map(_ItemKind::Const),
+        type_def(ty.clone()).map(_ItemKind::Type),
+        impl_def(ty.clone(), block.clone()).map(_ItemKind::Impl),
+        trait_def(ty.clone(), block.clone()).map(_ItemKind::Trait),
+        func_def(ty, block).map(_ItemKind::Func),
+    ))
+    .map_with_span(Node::new)
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    #[test]
+    fn every_kind_of_item() {
+        assert_eq!(sexp(_item(), "mod a;"), "(mod a)");
+        assert_eq!(sexp(_item(), "use a::b, c as d;"), "(use a::b, c as d)");
+        assert_eq!(
+            sexp(_item(), "const A: u8 = 1 + 2;"),
+            "(const A u8 (+ 1 2))"
+        );
+        assert_eq!(
+            sexp(_item(), "type A = (u8, B<C>);"),
+            "(type A (tup u8, B<C>))"
+        );
+        assert_eq!(
+            sexp(_item(), "impl A { fn f() {} }"),
+            "(impl A (fn f () () {}))"
+        );
+        assert_eq!(sexp(_item(), "impl A for B {}"), "(impl A for B)");
+        assert_eq!(
+            sexp(_item(), "trait A { fn f(); }"),
+            "(trait A (fn f () ();))"
+        );
+        assert_eq!(
+            sexp(_item(), "fn f(a: u8) -> u8 { a }"),
+            "(fn f (a: u8) u8 {a})"
+        );
+    }
+
+    #[test]
+    fn visibility_prefixes() {
+        assert_eq!(sexp(_item(), "pub mod a;"), "(mod pub a)");
+        assert_eq!(sexp(_item(), "pub use a;"), "(pub use a)");
+        assert_eq!(sexp(_item(), "pub const A: u8 = 1;"), "(const pub A u8 1)");
+        assert_eq!(sexp(_item(), "pub type A = u8;"), "(type pub A u8)");
+        assert_eq!(sexp(_item(), "pub trait A {}"), "(trait pub A)");
+        assert_eq!(sexp(_item(), "pub fn f() {}"), "(fn pub f () () {})");
+        assert!(rejects(_item(), "pub impl A {}"));
+        assert!(rejects(_item(), "pub pub fn f() {}"));
+    }
+
+    #[test]
+    fn outer_attributes_of_items() {
+        let item = parse_all(_item(), "#[a] #[b::c(d)] fn f() {}");
+        assert_eq!(item.inner().unwrap().attrs.inner().unwrap().0.len(), 2);
+        assert!(rejects(_item(), "#![a] fn f() {}"));
     }
 }
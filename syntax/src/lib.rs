@@ -18,6 +18,7 @@
 pub mod node;
 
 pub mod atom;
+pub mod attr;
 pub mod body;
 pub mod r#const;
 pub mod expr;
@@ -28,10 +29,15 @@ pub mod stmt;
 pub mod sugar;
 pub mod r#type;
 
+#[cfg(test)]
+mod sexp;
+
 pub use atom::*;
+pub use attr::*;
 pub use body::*;
 pub use expr::*;
 pub use item::*;
+pub use literal::Literal;
 pub use literal::*;
 pub use node::*;
 pub use r#const::*;
@@ -48,5 +54,102 @@ use chumsky::{prelude::*, util::MaybeRef};
 use compact_str::CompactString;
 use rclite::Arc;
 
+/// Syntax tree of a source file, its inner attributes apply to the module it defines
 #[derive(Clone, PartialEq, Debug)]
-pub struct Syntax<'i>(SVec<Item<'i>>);
+pub struct Syntax {
+    pub attrs: Attributes,
+    pub items: SVec<Item>,
+}
+
+/// Parses a source file into its items, along with every error found while lexing and parsing it
+pub fn parse(source: &str) -> (Option<Syntax>, Vec<Error>) {
+    let (tokens, mut errors) = TokenStream::new(source);
+    let (syntax, parser_errors) = attributes(_AttrStyle::Inner)
+        .then(
+            _item()
+                .recover_with(skip_then_retry_until(any().ignored(), end()))
+                // When no item follows, the rest of the source is skipped as a single item
+                .recover_with(via_parser(
+                    any()
+                        .repeated()
+                        .at_least(1)
+                        .map_with_span(|_, s| Node::skipped(s)),
+                ))
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .then_ignore(end())
+        .map(|(attrs, items)| Syntax {
+            attrs,
+            items: items.into(),
+        })
+        .parse(tokens)
+        .into_output_errors();
+    errors.extend(parser_errors.into_iter().map(Error::from));
+    (syntax, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    /// Items of the source along the offsets of the errors
+    fn recovered(source: &str) -> (Vec<String>, Vec<usize>) {
+        let (syntax, errors) = parse(source);
+        let items = syntax.expect("items are recovered").items;
+        let offsets = errors
+            .iter()
+            .map(|error| *error.span().unwrap().offset())
+            .collect();
+        (items.iter().map(Sexp::sexp).collect(), offsets)
+    }
+
+    #[test]
+    fn every_item_body_reports_its_errors() {
+        assert_eq!(
+            recovered("fn f() { 1 + ; } fn g() { 2 + ; }"),
+            (
+                vec![
+                    String::from("(fn f () () !)"),
+                    String::from("(fn g () () !)")
+                ],
+                vec![13, 30]
+            )
+        );
+        assert_eq!(
+            recovered("fn f() { if x { let = 1; } 2 } fn g() -> u8 { 1 }"),
+            (
+                vec![
+                    String::from("(fn f () () {(if x !) 2})"),
+                    String::from("(fn g () u8 {1})")
+                ],
+                vec![20]
+            )
+        );
+        assert_eq!(
+            recovered("impl A { fn f(1) {} } fn g() { [2 + ; ] }"),
+            (
+                vec![String::from("(impl A)"), String::from("(fn g () () !)")],
+                vec![14, 36]
+            )
+        );
+        assert_eq!(
+            recovered("fn f() {} fn h() {} x"),
+            (
+                vec![
+                    String::from("(fn f () () {})"),
+                    String::from("(fn h () () {})"),
+                    String::from("!")
+                ],
+                vec![20]
+            )
+        );
+        assert_eq!(
+            recovered("fn f() {} fn g("),
+            (
+                vec![String::from("(fn f () () {})"), String::from("!")],
+                vec![15]
+            )
+        );
+    }
+}
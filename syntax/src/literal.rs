@@ -18,26 +18,19 @@ use crate::*;
 
 node! {
     #[doc = "Define kinds of literals"]
-    case Literal<'i> {
+    case Literal {
         Bool(bool),
-        Int(usize),
-        Float(f32),
+        Int(u64, Option<Number>),
+        Float(f64, Option<Number>),
         Char(char),
-        Str(&'i str)
+        Str(Symbol)
     } {
         select! {
-            TokenKind::Literal(LiteralToken::Bool(bool)) = s => Node::new(_Literal::Bool(bool).into(), s),
-            TokenKind::Literal(LiteralToken::Int(int)) = s => Node::new(_Literal::Int(int).into(), s),
-            TokenKind::Literal(LiteralToken::Float(float)) = s => Node::new(_Literal::Float(float).into(), s),
-            TokenKind::Literal(LiteralToken::Char(ch)) = s => {
-                if ch.len() == 1 {
-                    Node::new(_Literal::Char(ch.chars().next().unwrap()).into(), s)
-                } else {
-                    // Node::new_err(Error::Parser { found: ch.to_compact_string(), help: Some(CompactString::new("Strings can only be de")), span: () })
-                    todo!()
-                }
-            },
-            TokenKind::Literal(LiteralToken::Str(str)) = s => Node::new(_Literal::Str(str).into(), s),
+            TokenKind::Literal(LiteralToken::Bool(bool)) = s => Node::new(_Literal::Bool(bool), s),
+            TokenKind::Literal(LiteralToken::Int(int, ty)) = s => Node::new(_Literal::Int(int, ty), s),
+            TokenKind::Literal(LiteralToken::Float(float, ty)) = s => Node::new(_Literal::Float(float, ty), s),
+            TokenKind::Literal(LiteralToken::Char(ch)) = s => Node::new(_Literal::Char(ch), s),
+            TokenKind::Literal(LiteralToken::Str(str)) = s => Node::new(_Literal::Str(str), s),
         }.boxed()
     }
 }
@@ -16,10 +16,46 @@
 
 use crate::*;
 
+use std::cmp::Ordering;
+
 /// Generic node type
 #[derive(Clone, PartialEq, Debug)]
 pub struct Node<T>(Result<(Arc<T>, Span), Error>);
 
+/// Error of every parser, which only becomes a diagnostic once parsing is done as most of them are discarded
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParserError<'i> {
+    found: Option<TokenKind<'i>>,
+    expected: Vec<Option<TokenKind<'i>>>,
+    span: Span,
+}
+
+/// Extra parser state shared by every parser
+pub type Extra<'i> = chumsky::extra::Err<ParserError<'i>>;
+
+/// Parser of a node from a token stream
+pub trait NodeParser<'i, T>:
+    chumsky::Parser<'i, TokenStream<'i>, T, Extra<'i>> + Clone + 'i
+{
+}
+
+impl<'i, T, P> NodeParser<'i, T> for P where
+    P: chumsky::Parser<'i, TokenStream<'i>, T, Extra<'i>> + Clone + 'i
+{
+}
+
+/// Skips the source between braces up to the closing one, so parsing goes on after an error within them
+pub(crate) fn skip_braces<'i, O: 'i>(
+    fallback: impl Fn(Span) -> O + Clone + 'i,
+) -> impl NodeParser<'i, O> {
+    nested_delimiters(
+        OP_BRACE,
+        CLS_BRACE,
+        [(OP_PAREN, CLS_PAREN), (OP_SQ_BRACKET, CLS_SQ_BRACKET)],
+        fallback,
+    )
+}
+
 impl<T> Node<T> {
     pub fn new(inner: T, span: Span) -> Node<T> {
         Node(Ok((Arc::new(inner), span)))
@@ -28,53 +64,98 @@ impl<T> Node<T> {
     pub fn new_err(inner: Error) -> Node<T> {
         Node(Err(inner))
     }
+
+    /// Node of the source skipped while recovering, whose errors were already reported
+    pub(crate) fn skipped(span: Span) -> Node<T> {
+        Node::new_err(Error::Parser {
+            found: Symbol::intern("skipped source"),
+            help: None,
+            span: Some(span),
+        })
+    }
+
+    /// Inner value of nodes that were parsed successfully
+    pub fn inner(&self) -> Option<&T> {
+        self.0.as_ref().ok().map(|(inner, _)| &**inner)
+    }
+}
+
+impl<T> Spanned for Node<T> {
+    fn span(&self) -> Span {
+        match &self.0 {
+            Ok((_, span)) => *span,
+            Err(error) => error.span().unwrap_or_default(),
+        }
+    }
 }
 
 #[macro_export]
 macro_rules! node {
+    (@parser $name:ident [$($generics:tt)*]) => {};
+    (@parser $name:ident [$($generics:tt)*] $parser:block) => {
+        paste::paste! {
+            pub fn [<_ $name:lower>]<'i>() -> impl $crate::node::NodeParser<'i, $name $($generics)*> $parser
+        }
+    };
     ($(#[$meta:meta])* case $name:ident$(<$param:lifetime>)? {$($variants:tt)*} $($parser:block)?) => {
         paste::paste! {
             $(#[$meta])*
             pub type $name$(< $param >)? = $crate::node::Node<[<_ $name>] $(< $param >)? >;
             #[derive(Clone, PartialEq, Debug)]
+            #[allow(clippy::large_enum_variant)]
             pub enum [<_ $name>] $(< $param >)? {
                 $($variants)*
             }
-            $(
-                pub fn [<_ $name:lower>]<'i>() -> impl chumsky::Parser<'i, kiban_lexer::TokenStream<'i>, $name<'i>, chumsky::extra::Err<Node<$name<'i>>>> {
-                    $parser
-                }
-            )?
         }
+        $crate::node!(@parser $name [$(<$param>)?] $($parser)?);
     };
-    ($(#[$meta:meta])* $name:ident$(<$param:lifetime>)? {$($fields:tt)*}$($parser:block)?) => {
+    ($(#[$meta:meta])* $name:ident$(<$param:lifetime>)? {$($field:ident: $ty:ty),* $(,)?}$($parser:block)?) => {
         paste::paste! {
             $(#[$meta])*
             pub type $name$(< $param >)? = $crate::node::Node<[<_ $name>] $(< $param >)? >;
             #[derive(Clone, PartialEq, Debug)]
             pub struct [<_ $name>] $(< $param >)?{
-                $($fields)*
+                $(pub $field: $ty),*
             }
-            $(
-                pub fn [<_ $name:lower>]<'i>() -> impl chumsky::Parser<'i, kiban_lexer::TokenStream<'i>, $name<'i>, chumsky::extra::Err<Node<$name<'i>>>> $parser
-            )?
         }
+        $crate::node!(@parser $name [$(<$param>)?] $($parser)?);
     };
-    ($(#[$meta:meta])* $name:ident$(<$param:lifetime>)? ($($fields:tt)*) $($parser:block)?) => {
+    ($(#[$meta:meta])* $name:ident$(<$param:lifetime>)? ($($ty:ty),* $(,)?) $($parser:block)?) => {
         paste::paste! {
-            pub type $name $(< $param >)? = crate::node::Node<[<_ $name>] $(< $param >)? >;
+            $(#[$meta])*
+            pub type $name $(< $param >)? = $crate::node::Node<[<_ $name>] $(< $param >)? >;
             #[derive(Clone, PartialEq, Debug)]
             pub struct [<_ $name>] $(< $param >)? (
-                $($fields)*
+                $(pub $ty),*
             );
-            $(
-                pub fn [<_ $name:lower>]<'i>() -> impl chumsky::Parser<'i, kiban_lexer::TokenStream<'i>, $name<'i>, chumsky::extra::Err<Node<$name<'i>>>> $parser
-            )?
         }
+        $crate::node!(@parser $name [$(<$param>)?] $($parser)?);
     };
 }
 
-impl<'i, T> chumsky::error::Error<'i, TokenStream<'i>> for Node<T> {
+impl<'i> ParserError<'i> {
+    /// Error of a token found where others were expected
+    pub(crate) fn unexpected(
+        found: TokenKind<'i>,
+        expected: impl IntoIterator<Item = TokenKind<'i>>,
+        span: Span,
+    ) -> Self {
+        Self {
+            found: Some(found),
+            expected: expected.into_iter().map(Some).collect(),
+            span,
+        }
+    }
+}
+
+/// Describes a token for diagnostics, the end of input is described when there is none
+fn describe(token: Option<&TokenKind<'_>>) -> Symbol {
+    token
+        .and_then(|token| token.origin())
+        .unwrap_or_else(|| Symbol::intern("end of input"))
+}
+
+impl<'i> chumsky::error::Error<'i, TokenStream<'i>> for ParserError<'i> {
     fn expected_found<
         E: IntoIterator<
             Item = Option<MaybeRef<'i, <TokenStream<'i> as chumsky::prelude::Input<'i>>::Token>>,
@@ -84,21 +165,43 @@ impl<'i, T> chumsky::error::Error<'i, TokenStream<'i>> for Node<T> {
         found: Option<MaybeRef<'i, <TokenStream<'i> as chumsky::prelude::Input<'i>>::Token>>,
         span: <TokenStream<'i> as chumsky::prelude::Input<'i>>::Span,
     ) -> Self {
-        Self(Err(Error::Parser {
-            found: found.unwrap().origin().unwrap(),
-            help: {
-                let mut ret: Option<CompactString> = None;
-                expected.into_iter().for_each(|s| {
-                    let token = s.unwrap().origin().unwrap();
-                    if let Some(ret_content) = &mut ret {
-                        ret_content.push_str(token.as_str())
-                    } else {
-                        ret = Some(token)
-                    }
-                });
-                ret
-            },
-            span: Some(span),
-        }))
+        Self {
+            found: found.map(MaybeRef::into_inner),
+            expected: expected
+                .into_iter()
+                .map(|token| token.map(MaybeRef::into_inner))
+                .collect(),
+            span,
+        }
+    }
+
+    /// Errors at the same place expect the tokens of both, otherwise the furthest one wins
+    fn merge(mut self, mut other: Self) -> Self {
+        match self.span.offset().cmp(other.span.offset()) {
+            Ordering::Less => other,
+            Ordering::Greater => self,
+            Ordering::Equal => {
+                self.expected.append(&mut other.expected);
+                self
+            }
+        }
+    }
+}
+
+impl From<ParserError<'_>> for Error {
+    fn from(error: ParserError<'_>) -> Self {
+        let mut expected = error
+            .expected
+            .iter()
+            .map(|token| format!("`{}`", describe(token.as_ref())))
+            .collect::<Vec<_>>();
+        expected.sort();
+        expected.dedup();
+        Error::Parser {
+            found: describe(error.found.as_ref()),
+            help: (!expected.is_empty())
+                .then(|| CompactString::from(format!("Expected {}", expected.join(", ")))),
+            span: Some(error.span),
+        }
     }
 }
@@ -0,0 +1,547 @@
+// Kiban
+// Copyright (C) 2022 Oscar
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::*;
+
+use binary::_Binary;
+use interpolation::_FormatSegment;
+use range::_Range;
+use unary::_Unary;
+
+/// Renders nodes as s-expressions, so tests can compare the shape of syntax trees
+pub(crate) trait Sexp {
+    fn sexp(&self) -> String;
+}
+
+/// Parses the whole source with the parser, failing with every error found when it cannot
+pub(crate) fn parse_all<'i, T>(parser: impl NodeParser<'i, T>, source: &'i str) -> T {
+    let (tokens, errors) = TokenStream::new(source);
+    assert!(errors.is_empty(), "{source:?}: {errors:?}");
+    parser
+        .then_ignore(end())
+        .parse(tokens)
+        .into_result()
+        .unwrap_or_else(|errors| {
+            panic!(
+                "{source:?}: {:?}",
+                errors.into_iter().map(Error::from).collect::<Vec<_>>()
+            )
+        })
+}
+
+/// Whether the parser rejects the whole source
+pub(crate) fn rejects<'i, T>(parser: impl NodeParser<'i, T>, source: &'i str) -> bool {
+    let (tokens, _) = TokenStream::new(source);
+    parser.then_ignore(end()).parse(tokens).has_errors()
+}
+
+/// Renders what the parser gives for the whole source
+pub(crate) fn sexp<'i, T: Sexp>(parser: impl NodeParser<'i, T>, source: &'i str) -> String {
+    parse_all(parser, source).sexp()
+}
+
+/// Renders each node and joins them with spaces
+fn join<T: Sexp>(nodes: &[T]) -> String {
+    nodes.iter().map(Sexp::sexp).collect::<Vec<_>>().join(" ")
+}
+
+/// Renders a list with a head, leaving out the space when it is empty
+fn list(head: &str, rest: String) -> String {
+    match rest.is_empty() {
+        true => format!("({head})"),
+        false => format!("({head} {rest})"),
+    }
+}
+
+impl<T: Sexp> Sexp for Node<T> {
+    fn sexp(&self) -> String {
+        self.inner().map_or_else(|| String::from("!"), Sexp::sexp)
+    }
+}
+
+impl<T: Sexp> Sexp for Option<T> {
+    fn sexp(&self) -> String {
+        self.as_ref().map_or_else(String::new, Sexp::sexp)
+    }
+}
+
+impl Sexp for _Ident {
+    fn sexp(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl Sexp for _Lifetime {
+    fn sexp(&self) -> String {
+        format!("'{}", self.0.sexp())
+    }
+}
+
+impl Sexp for _Path {
+    fn sexp(&self) -> String {
+        let mut path = self.ident.sexp();
+        if let Some(generics) = self
+            .generics
+            .inner()
+            .filter(|generics| !generics.0.is_empty())
+        {
+            let generics = generics.0.iter().map(Sexp::sexp).collect::<Vec<_>>();
+            path.push_str(&format!("<{}>", generics.join(", ")));
+        }
+        if let Some(subpath) = &self.subpath {
+            path.push_str(&format!("::{}", subpath.sexp()));
+        }
+        path
+    }
+}
+
+impl Sexp for _GenericTypes {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Lifetime(lifetime) => lifetime.sexp(),
+            Self::Name(name) => name.sexp(),
+            Self::Type(ty) => ty.sexp(),
+        }
+    }
+}
+
+impl Sexp for _Literal {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Bool(bool) => bool.to_string(),
+            Self::Int(int, _) => int.to_string(),
+            Self::Float(float, _) => float.to_string(),
+            Self::Char(ch) => format!("{ch:?}"),
+            Self::Str(str) => format!("{:?}", str.as_str()),
+        }
+    }
+}
+
+impl Sexp for _Binary {
+    fn sexp(&self) -> String {
+        String::from(match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Exp => "**",
+            Self::Remainder => "%",
+            Self::LSht => "<<",
+            Self::RSht => ">>",
+            Self::Eq => "==",
+            Self::NotEq => "!=",
+            Self::Gr => ">",
+            Self::Less => "<",
+            Self::GrEq => ">=",
+            Self::LessEq => "<=",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::XOr => "^",
+        })
+    }
+}
+
+impl Sexp for _Const {
+    fn sexp(&self) -> String {
+        self.0.sexp()
+    }
+}
+
+impl Sexp for _Range {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Bounded(start, end) => format!("(.. {} {})", start.sexp(), end.sexp()),
+            Self::From(start) => format!("(.. {} _)", start.sexp()),
+            Self::To(end) => format!("(.. _ {})", end.sexp()),
+            Self::Inclusive(start, end) => format!("(..= {} {})", start.sexp(), end.sexp()),
+            Self::ToInclusive(end) => format!("(..= _ {})", end.sexp()),
+            Self::Full => String::from("(.. _ _)"),
+        }
+    }
+}
+
+impl Sexp for _Expr {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Path(path) => path.sexp(),
+            Self::Underscore => String::from("_"),
+            Self::Parenthesized(expr) => format!("(paren {})", expr.sexp()),
+            Self::Refer(expr) => format!("(& {})", expr.sexp()),
+            Self::Unary(op, expr) => {
+                let op = match op.inner() {
+                    Some(_Unary::Deref) => "*",
+                    Some(_Unary::Negative) => "-",
+                    Some(_Unary::Negation) => "!",
+                    None => "!",
+                };
+                format!("({op} {})", expr.sexp())
+            }
+            Self::Binary { op, lhs, rhs } => {
+                format!("({} {} {})", op.sexp(), lhs.sexp(), rhs.sexp())
+            }
+            Self::Literal(literal) => literal.sexp(),
+            Self::Format(format) => {
+                let segments = format.inner().map_or_else(Vec::new, |format| {
+                    format
+                        .0
+                        .iter()
+                        .map(|segment| match segment.inner() {
+                            Some(_FormatSegment::Text(text)) => format!("{:?}", text.as_str()),
+                            Some(_FormatSegment::Expr(expr, _)) => format!("{{{}}}", expr.sexp()),
+                            None => String::from("!"),
+                        })
+                        .collect()
+                });
+                list("f", segments.join(" "))
+            }
+            Self::Struct(path, _) => list("struct", path.sexp()),
+            Self::Block(block) => block.sexp(),
+            Self::Array(exprs) => format!("[{}]", join(exprs)),
+            Self::Tup(exprs) => list(
+                "tup",
+                exprs
+                    .inner()
+                    .map_or_else(String::new, |exprs| join(&exprs.0)),
+            ),
+            Self::Closure(scope, closure) => {
+                let head = match scope.inner().is_some_and(|scope| scope.0) {
+                    true => "move fn",
+                    false => "fn",
+                };
+                list(head, closure.sexp())
+            }
+            Self::Range(range) => range.sexp(),
+            Self::Assign(deref, ident, op, expr) => {
+                let deref = match deref.inner().is_some_and(|deref| deref.0) {
+                    true => "*",
+                    false => "",
+                };
+                format!("({}= {deref}{} {})", op.sexp(), ident.sexp(), expr.sexp())
+            }
+            Self::Field(expr, ident) => format!("(. {} {})", expr.sexp(), ident.sexp()),
+            Self::Call(expr, args) => list(
+                "call",
+                format!("{} {}", expr.sexp(), args.sexp()).trim_end().into(),
+            ),
+            Self::Method { def, method, args } => list(
+                "method",
+                format!("{} {} {}", def.sexp(), method.sexp(), args.sexp())
+                    .trim_end()
+                    .into(),
+            ),
+            Self::Index(expr, index) => format!("(index {} {})", expr.sexp(), index.sexp()),
+            Self::Cast(expr, ty) => format!("(as {} {})", expr.sexp(), ty.sexp()),
+            Self::Cond { check, then, not } => match not {
+                Some(not) => format!("(if {} {} {})", check.sexp(), then.sexp(), not.sexp()),
+                None => format!("(if {} {})", check.sexp(), then.sexp()),
+            },
+            Self::Loop(block) => format!("(loop {})", block.sexp()),
+            Self::ForLoop { item, iter, block } => {
+                format!("(for {} {} {})", item.sexp(), iter.sexp(), block.sexp())
+            }
+            Self::While { check, block } => format!("(while {} {})", check.sexp(), block.sexp()),
+            Self::Continue => String::from("continue"),
+            Self::Break => String::from("break"),
+        }
+    }
+}
+
+impl Sexp for _Args {
+    fn sexp(&self) -> String {
+        join(&self.0)
+    }
+}
+
+impl Sexp for _MutTy {
+    fn sexp(&self) -> String {
+        match self.mutable.inner().is_some_and(|mutable| mutable.0) {
+            true => format!("mut {}", self.ty.sexp()),
+            false => self.ty.sexp(),
+        }
+    }
+}
+
+impl Sexp for _MutExpr {
+    fn sexp(&self) -> String {
+        match self.mutable.inner().is_some_and(|mutable| mutable.0) {
+            true => format!("mut {}", self.expr.sexp()),
+            false => self.expr.sexp(),
+        }
+    }
+}
+
+impl Sexp for _StructTy {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Tup(tys) => format!("({})", tys.sexp()),
+            Self::Field(fields) => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|field| field.inner().map_or_else(
+                        || String::from("!"),
+                        |field| format!("{}: {}", field.name.sexp(), field.ty.sexp())
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl Sexp for _TupTy {
+    fn sexp(&self) -> String {
+        self.0.iter().map(Sexp::sexp).collect::<Vec<_>>().join(", ")
+    }
+}
+
+impl Sexp for _VariantTy {
+    fn sexp(&self) -> String {
+        format!("{}{}", self.name.sexp(), self.inner.sexp())
+    }
+}
+
+impl Sexp for _Type {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Null => String::from("()"),
+            Self::Infer => String::from("_"),
+            Self::Paren(ty) => format!("(paren {})", ty.sexp()),
+            Self::Ref(lifetime, ty) => match lifetime {
+                Some(lifetime) => format!("(& {} {})", lifetime.sexp(), ty.sexp()),
+                None => format!("(& {})", ty.sexp()),
+            },
+            Self::Ptr(ty) => format!("(ptr {})", ty.sexp()),
+            Self::Path(path) => path.sexp(),
+            Self::Boolean => String::from("bool"),
+            Self::Integer(number) => {
+                let sign = if *number.signed() { "i" } else { "u" };
+                format!("{sign}{}", number.size().bits())
+            }
+            Self::Float(number) => format!("f{}", number.size().bits()),
+            Self::Char => String::from("char"),
+            Self::Array(ty, len) => format!("[{}; {}]", ty.sexp(), len.sexp()),
+            Self::Slice(ty) => format!("[{}]", ty.sexp()),
+            Self::Tup(tys) => format!("(tup {})", tys.sexp()),
+            Self::Struct(ty) => ty.sexp(),
+            Self::Enum(variants) => list(
+                "|",
+                variants
+                    .inner()
+                    .map_or_else(String::new, |variants| join(&variants.0)),
+            ),
+            Self::LocalSelf => String::from("Self"),
+            Self::FnSig(sig) => list("Fn", sig.sexp()),
+        }
+    }
+}
+
+impl Sexp for _Param {
+    fn sexp(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{}: {}", name.sexp(), self.ty.sexp()),
+            None => self.ty.sexp(),
+        }
+    }
+}
+
+impl Sexp for _Signature {
+    fn sexp(&self) -> String {
+        let generics = self
+            .generics
+            .inner()
+            .filter(|generics| !generics.0.is_empty())
+            .map_or_else(String::new, |generics| format!("<{}> ", join(&generics.0)));
+        let params = self.params.inner().map_or_else(String::new, |params| {
+            params
+                .0
+                .iter()
+                .map(Sexp::sexp)
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        format!("{generics}({params}) {}", self.expect.sexp())
+    }
+}
+
+impl Sexp for _Closure {
+    fn sexp(&self) -> String {
+        match &self.block {
+            Some(block) => format!("{} {}", self.sig.sexp(), block.sexp()),
+            None => format!("{};", self.sig.sexp()),
+        }
+    }
+}
+
+impl Sexp for _Block {
+    fn sexp(&self) -> String {
+        format!("{{{}}}", join(&self.stmts))
+    }
+}
+
+impl Sexp for _Stmt {
+    fn sexp(&self) -> String {
+        self.kind.sexp()
+    }
+}
+
+impl Sexp for _StmtKind {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Item(item) => item.sexp(),
+            Self::Expression(expr) => expr.sexp(),
+            Self::Semi(expr) => format!("{};", expr.sexp()),
+            Self::Declare {
+                mutable,
+                name,
+                ty,
+                val,
+            } => {
+                let name = match mutable.inner().is_some_and(|mutable| mutable.0) {
+                    true => format!("mut {}", name.sexp()),
+                    false => name.sexp(),
+                };
+                let val = val
+                    .as_ref()
+                    .map_or_else(String::new, |val| format!(" {}", val.sexp()));
+                format!("(let {name} {}{val})", ty.sexp())
+            }
+        }
+    }
+}
+
+impl Sexp for _Item {
+    fn sexp(&self) -> String {
+        self.kind.sexp()
+    }
+}
+
+/// Renders the visibility as a prefix of the name
+fn visible(vis: &Visibility, name: &Ident) -> String {
+    match vis.inner() {
+        Some(_Visibility::Public) => format!("pub {}", name.sexp()),
+        _ => name.sexp(),
+    }
+}
+
+impl Sexp for _FuncDef {
+    fn sexp(&self) -> String {
+        let name = visible(&self.visible, &self.name);
+        let extend = match self.extend.inner().is_some_and(|extend| extend.0) {
+            true => " +=",
+            false => "",
+        };
+        let variants = self.variants.inner().map_or_else(String::new, |variants| {
+            variants
+                .0
+                .iter()
+                .map(Sexp::sexp)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        });
+        format!("(fn {name}{extend} {variants})")
+    }
+}
+
+impl Sexp for _ItemKind {
+    fn sexp(&self) -> String {
+        match self {
+            Self::Module(module) => module.inner().map_or_else(
+                || String::from("!"),
+                |module| format!("(mod {})", visible(&module.vis, &module.names)),
+            ),
+            Self::Import(import) => import.inner().map_or_else(
+                || String::from("!"),
+                |import| {
+                    let names = import
+                        .names
+                        .iter()
+                        .map(|name| {
+                            name.inner().map_or_else(
+                                || String::from("!"),
+                                |name| match &name.alias {
+                                    Some(alias) => {
+                                        format!("{} as {}", name.path.sexp(), alias.sexp())
+                                    }
+                                    None => name.path.sexp(),
+                                },
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    match import.vis.inner() {
+                        Some(_Visibility::Public) => format!("(pub use {names})"),
+                        _ => format!("(use {names})"),
+                    }
+                },
+            ),
+            Self::Const(def) => def.inner().map_or_else(
+                || String::from("!"),
+                |def| {
+                    format!(
+                        "(const {} {} {})",
+                        visible(&def.vis, &def.name),
+                        def.ty.sexp(),
+                        def.value.sexp()
+                    )
+                },
+            ),
+            Self::Type(def) => def.inner().map_or_else(
+                || String::from("!"),
+                |def| format!("(type {} {})", visible(&def.vis, &def.name), def.ty.sexp()),
+            ),
+            Self::Impl(def) => def.inner().map_or_else(
+                || String::from("!"),
+                |def| {
+                    let name = match &def.spec {
+                        Some(spec) => format!("{} for {}", spec.sexp(), def.name.sexp()),
+                        None => def.name.sexp(),
+                    };
+                    list(
+                        "impl",
+                        format!("{name} {}", join(&def.funcs)).trim_end().into(),
+                    )
+                },
+            ),
+            Self::Trait(def) => def.inner().map_or_else(
+                || String::from("!"),
+                |def| {
+                    let name = visible(&def.vis, &def.name);
+                    list(
+                        "trait",
+                        format!("{name} {}", join(&def.funcs)).trim_end().into(),
+                    )
+                },
+            ),
+            Self::Func(func) => func.sexp(),
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_nodes_as_s_expressions() {
+        assert_eq!(sexp(_expr(), "a + b * c"), "(+ a (* b c))");
+        assert_eq!(sexp(_expr(), "f(a, [b])"), "(call f a [b])");
+        assert_eq!(sexp(_type(), "&'a [u8; 4]"), "(& 'a [u8; 4])");
+        assert_eq!(
+            sexp(_item(), "pub fn f(a: u8) -> u8 { a; }"),
+            "(fn pub f (a: u8) u8 {a;})"
+        );
+    }
+}
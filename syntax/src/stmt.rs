@@ -17,16 +17,175 @@
 use crate::*;
 
 node! {
-    #[doc = "Define statements"]
-    case Stmt<'i> {
-        Item(Item<'i>),
-        Expression(Expr<'i>),
-        Semi(Expr<'i>),
+    #[doc = "Define a statement along with its attributes"]
+    Stmt {
+        attrs: Attributes,
+        kind: StmtKind
+    } {
+        statements(_expr(), _type(), _block(), _item())
+    }
+}
+
+node! {
+    #[doc = "Define kinds of statements"]
+    case StmtKind {
+        Item(Item),
+        Expression(Expr),
+        Semi(Expr),
         Declare {
             mutable: Mutable,
-            name: Ident<'i>,
-            ty: Type<'i>,
-            val: Option<Expr<'i>>,
+            name: Ident,
+            ty: Type,
+            val: Option<Expr>,
         },
     }
 }
+
+/// Parses statements, expressions need a semicolon unless they end with a block or close it
+pub(crate) fn statements<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+    block: impl NodeParser<'i, Block>,
+    item: impl NodeParser<'i, Item>,
+) -> impl NodeParser<'i, Stmt> {
+    let declare = just(LET)
+        .ignore_then(_mutable())
+        .then(_ident())
+        .then(choice((
+            just(COLON).ignore_then(ty.clone()),
+            empty().map_with_span(|_, s| Node::new(_Type::Infer, s)),
+        )))
+        .then(assign().then(expr.clone()).or_not())
+        .validate(|(declared, val), _, emitter| {
+            let val = val.map(|(split, val)| {
+                check_assign(&declared.1, split, emitter);
+                val
+            });
+            (declared, val)
+        })
+        .then_ignore(just(SEMICOLON))
+        .map_with_span(|(((mutable, name), ty), val), s| {
+            Node::new(
+                _StmtKind::Declare {
+                    mutable,
+                    name,
+                    ty,
+                    val,
+                },
+                s,
+            )
+        });
+    let block_like = block_like(heads(expr.clone(), ty.clone(), block.clone()), block)
+        .then(just(SEMICOLON).or_not())
+        .map_with_span(|(expr, semi), s| match semi {
+            Some(_) => Node::new(_StmtKind::Semi(expr), s),
+            None => Node::new(_StmtKind::Expression(expr), s),
+        });
+    let expression = expr
+        .then(choice((
+            just(SEMICOLON).to(true),
+            just(CLS_BRACE).rewind().to(false),
+        )))
+        .map_with_span(|(expr, semi), s| match semi {
+            true => Node::new(_StmtKind::Semi(expr), s),
+            false => Node::new(_StmtKind::Expression(expr), s),
+        });
+    choice((
+        item.map_with_span(|item, s: Span| {
+            let attrs = Node::new(_Attributes(SVec::new()), Span::new(*s.offset(), 0));
+            let kind = Node::new(_StmtKind::Item(item), s);
+            Node::new(_Stmt { attrs, kind }, s)
+        }),
+        _attributes()
+            .then(choice((declare, block_like, expression)))
+            .map_with_span(|(attrs, kind), s| Node::new(_Stmt { attrs, kind }, s)),
+    ))
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    #[test]
+    fn let_declarations() {
+        assert_eq!(sexp(_block(), "{ let x; }"), "{(let x _)}");
+        assert_eq!(sexp(_block(), "{ let x = 1; }"), "{(let x _ 1)}");
+        assert_eq!(sexp(_block(), "{ let x: u8; }"), "{(let x u8)}");
+        assert_eq!(
+            sexp(_block(), "{ let mut x: u8 = a + b; }"),
+            "{(let mut x u8 (+ a b))}"
+        );
+        assert!(rejects(_block(), "{ let x = 1 }"));
+        assert!(rejects(_block(), "{ let = 1; }"));
+    }
+
+    #[test]
+    fn generics_closed_before_the_assignment() {
+        assert_eq!(sexp(_block(), "{ let x: A<u8>= a; }"), "{(let x A<u8> a)}");
+        assert_eq!(
+            sexp(_block(), "{ let x: Vec<Vec<u8>>= a; }"),
+            "{(let x Vec<Vec<u8>> a)}"
+        );
+        assert_eq!(
+            sexp(_block(), "{ let x: &A<u8>= a; }"),
+            "{(let x (& A<u8>) a)}"
+        );
+        assert_eq!(
+            sexp(_block(), "{ let x = a >= b; }"),
+            "{(let x _ (>= a b))}"
+        );
+        assert_eq!(sexp(_item(), "const A: B<u8>= 1;"), "(const A B<u8> 1)");
+        assert!(rejects(_block(), "{ let x: u8 >= 1; }"));
+        assert!(rejects(_block(), "{ let x: A<B> >= 1; }"));
+        assert!(rejects(_block(), "{ let x: A<B<u8>= 1; }"));
+        assert!(rejects(_block(), "{ let x: A<B>>= 1; }"));
+        assert!(rejects(_item(), "const A: u8 >= 1;"));
+    }
+
+    #[test]
+    fn trailing_expression_or_semicolon() {
+        assert_eq!(sexp(_block(), "{ a }"), "{a}");
+        assert_eq!(sexp(_block(), "{ a; }"), "{a;}");
+        assert_eq!(sexp(_block(), "{ a; b }"), "{a; b}");
+        assert_eq!(sexp(_block(), "{ a(); b(); }"), "{(call a); (call b);}");
+        assert!(rejects(_block(), "{ a b }"));
+    }
+
+    #[test]
+    fn block_like_statements_need_no_semicolon() {
+        assert_eq!(sexp(_block(), "{ if a {} b }"), "{(if a {}) b}");
+        assert_eq!(sexp(_block(), "{ loop {}; b }"), "{(loop {}); b}");
+        assert_eq!(
+            sexp(_block(), "{ while a {} for i in 0.. {} }"),
+            "{(while a {}) (for i (.. 0 _) {})}"
+        );
+        assert_eq!(sexp(_block(), "{ { a } { b } }"), "{{a} {b}}");
+        assert_eq!(
+            sexp(_block(), "{ if a { 1 } else { 2 } }"),
+            "{(if a {1} {2})}"
+        );
+    }
+
+    #[test]
+    fn nested_items() {
+        assert_eq!(
+            sexp(_block(), "{ fn g() -> u8 { 1 } const A: u8 = 2; g() + A }"),
+            "{(fn g () u8 {1}) (const A u8 2) (+ (call g) A)}"
+        );
+        assert_eq!(sexp(_block(), "{ use a::b; b }"), "{(use a::b) b}");
+    }
+
+    #[test]
+    fn attributes_of_statements() {
+        let block = parse_all(_block(), "{ #![a] #[b] let x = 1; #[c] x }");
+        let block = block.inner().unwrap();
+        assert_eq!(block.attrs.inner().unwrap().0.len(), 1);
+        let attrs = block
+            .stmts
+            .iter()
+            .map(|stmt| stmt.inner().unwrap().attrs.inner().unwrap().0.len())
+            .collect::<Vec<_>>();
+        assert_eq!(attrs, [1, 1]);
+    }
+}
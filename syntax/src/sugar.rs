@@ -21,12 +21,38 @@ node! {
     case Visibility {
         Private,
         Public
+    } {
+        just(PUB).or_not().map_with_span(|vis, s| match vis {
+            Some(_) => Node::new(_Visibility::Public, s),
+            None => Node::new(_Visibility::Private, s),
+        })
+    }
+}
+
+node! {
+    #[doc = "Define whether an attribute applies to the item that follows it or to the one that encloses it"]
+    case AttrStyle {
+        Outer,
+        Inner
     }
 }
 
 node! {
     #[doc = "Define whether values in scope should be moved to closure"]
-    MoveScope(bool)
+    MoveScope(bool) {
+        just(MOVE)
+            .or_not()
+            .map_with_span(|scope, s| Node::new(_MoveScope(scope.is_some()), s))
+    }
+}
+
+node! {
+    #[doc = "Define whether a function appends its variants to the one of the same name"]
+    Extend(bool) {
+        just(PLUS_EQ)
+            .or_not()
+            .map_with_span(|extend, s| Node::new(_Extend(extend.is_some()), s))
+    }
 }
 
 node! {
@@ -36,5 +62,9 @@ node! {
 
 node! {
     #[doc = "Define whether a definition is mutable"]
-    Mutable(bool)
+    Mutable(bool) {
+        just(MUT)
+            .or_not()
+            .map_with_span(|mutable, s| Node::new(_Mutable(mutable.is_some()), s))
+    }
 }
@@ -18,74 +18,348 @@ use crate::*;
 
 node! {
     #[doc = "Define types"]
-    case Type<'i> {
+    case Type {
         Null,
         Infer,
-        Paren(Type<'i>),
-        Ref(Option<Lifetime<'i>>, MutTy<'i>),
-        Ptr(MutTy<'i>),
-        Path(Path<'i>),
+        Paren(Type),
+        Ref(Option<Lifetime>, MutTy),
+        Ptr(MutTy),
+        Path(Path),
         Boolean,
         Integer(Number),
         Float(Number),
         Char,
-        Array(Type<'i>, Const<'i>),
-        Slice(Type<'i>),
-        Tup(TupTy<'i>),
-        Struct(StructTy<'i>),
-        Enum(EnumTy<'i>),
+        Array(Type, Const),
+        Slice(Type),
+        Tup(TupTy),
+        Struct(StructTy),
+        Enum(EnumTy),
         LocalSelf,
-        FnSig(Signature<'i>),
+        FnSig(Signature),
+    } {
+        recursive(|ty| types(_expr(), ty))
     }
 }
 
 node! {
     #[doc = "Define a type"]
-    TypeDef<'i> {
+    TypeDef {
         vis: Visibility,
-        name: Ident<'i>,
-        ty: Type<'i>,
+        name: Ident,
+        ty: Type,
+    } {
+        type_def(_type())
     }
 }
 
 node! {
     #[doc = "Define a list of types"]
-    TupTy<'i>(SVec<Type<'i>>)
+    TupTy(SVec<Type>)
 }
 
 node! {
     #[doc = "Define structs"]
-    case StructTy<'i> {
-        Tup(TupTy<'i>),
-        Field(SVec<FieldTy<'i>>)
+    case StructTy {
+        Tup(TupTy),
+        Field(SVec<FieldTy>)
     }
 }
 
 node! {
     #[doc = "Define the field of structs"]
-    FieldTy<'i> {
-        name: Ident<'i>,
-        ty: Type<'i>
+    FieldTy {
+        attrs: Attributes,
+        name: Ident,
+        ty: Type
     }
 }
 
 node! {
     #[doc = "Defines enums"]
-    EnumTy<'i>(SVec<VariantTy<'i>>)
+    EnumTy(SVec<VariantTy>)
 }
 
 node! {
     #[doc = "Define enum variants"]
-    VariantTy<'i> {
-        name: Ident<'i>,
-        inner: Option<StructTy<'i>>
+    VariantTy {
+        attrs: Attributes,
+        name: Ident,
+        inner: Option<StructTy>
     }
 }
 
 node! {
     #[doc = "Defines if the type is mutable"]
-    MutTy<'i> {
+    MutTy {
         mutable: Mutable,
-        ty: Type<'i>
+        ty: Type
+    }
+}
+
+/// Parses type definitions
+pub(crate) fn type_def<'i>(ty: impl NodeParser<'i, Type>) -> impl NodeParser<'i, TypeDef> {
+    _visibility()
+        .then_ignore(just(TYPE))
+        .then(_ident())
+        .then_ignore(just(EQ))
+        .then(ty)
+        .then_ignore(just(SEMICOLON))
+        .map_with_span(|((vis, name), ty), s| Node::new(_TypeDef { vis, name, ty }, s))
+}
+
+/// Parses types
+pub(crate) fn types<'i>(
+    expr: impl NodeParser<'i, Expr>,
+    ty: impl NodeParser<'i, Type>,
+) -> impl NodeParser<'i, Type> {
+    let mut_ty = _mutable()
+        .then(ty.clone())
+        .map_with_span(|(mutable, ty), s| Node::new(_MutTy { mutable, ty }, s));
+
+    let reference = choice((just(AMP).to(false), just(AND).to(true)))
+        .map_with_span(|double, s: Span| (double, s))
+        .then(_lifetime().or_not())
+        .then(mut_ty.clone())
+        .map_with_span(|(((double, amp), lifetime), ty), s: Span| {
+            let inner = Span::new(amp.offset() + double as usize, s.length() - double as usize);
+            let reference = Node::new(_Type::Ref(lifetime, ty), inner);
+            match double {
+                // `&&` is lexed as a single token, so it is split into two references
+                true => {
+                    let mutable = Node::new(_Mutable(false), Span::new(*amp.offset(), 0));
+                    let ty = Node::new(
+                        _MutTy {
+                            mutable,
+                            ty: reference,
+                        },
+                        inner,
+                    );
+                    Node::new(_Type::Ref(None, ty), s)
+                }
+                false => reference,
+            }
+        });
+
+    let pointer = choice((just(STAR).to(false), just(STAR_STAR).to(true)))
+        .map_with_span(|double, s: Span| (double, s))
+        .then(mut_ty)
+        .map_with_span(|((double, star), ty), s: Span| {
+            let inner = Span::new(
+                star.offset() + double as usize,
+                s.length() - double as usize,
+            );
+            let pointer = Node::new(_Type::Ptr(ty), inner);
+            match double {
+                // `**` is lexed as a single token, so it is split into two pointers
+                true => {
+                    let mutable = Node::new(_Mutable(false), Span::new(*star.offset(), 0));
+                    let ty = Node::new(
+                        _MutTy {
+                            mutable,
+                            ty: pointer,
+                        },
+                        inner,
+                    );
+                    Node::new(_Type::Ptr(ty), s)
+                }
+                false => pointer,
+            }
+        });
+
+    // A single type is only a tuple when a comma follows it
+    let tuple = ty
+        .clone()
+        .separated_by(just(COMMA))
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .then(just(COMMA).or_not())
+        .or_not()
+        .delimited_by(just(OP_PAREN), just(CLS_PAREN))
+        .map_with_span(|tys, s| match tys {
+            None => Node::new(_Type::Null, s),
+            Some((mut tys, None)) if tys.len() == 1 => {
+                Node::new(_Type::Paren(tys.pop().unwrap()), s)
+            }
+            Some((tys, _)) => Node::new(_Type::Tup(Node::new(_TupTy(tys.into()), s)), s),
+        });
+
+    let array = ty
+        .clone()
+        .then(
+            just(SEMICOLON)
+                .ignore_then(expr)
+                .map_with_span(|expr, s| Node::new(_Const(expr), s))
+                .or_not(),
+        )
+        .delimited_by(just(OP_SQ_BRACKET), just(CLS_SQ_BRACKET))
+        .map_with_span(|(ty, len), s| match len {
+            Some(len) => Node::new(_Type::Array(ty, len), s),
+            None => Node::new(_Type::Slice(ty), s),
+        });
+
+    let fields = _attributes()
+        .then(_ident())
+        .then_ignore(just(COLON))
+        .then(ty.clone())
+        .map_with_span(|((attrs, name), ty), s| Node::new(_FieldTy { attrs, name, ty }, s))
+        .separated_by(just(COMMA))
+        .allow_trailing()
+        .collect::<Vec<_>>()
+        .delimited_by(just(OP_BRACE), just(CLS_BRACE))
+        .map_with_span(|fields, s| Node::new(_StructTy::Field(fields.into()), s))
+        .boxed();
+    let struct_ty = choice((
+        ty.clone()
+            .separated_by(just(COMMA))
+            .allow_trailing()
+            .collect::<Vec<_>>()
+            .delimited_by(just(OP_PAREN), just(CLS_PAREN))
+            .map_with_span(|tys, s| Node::new(_StructTy::Tup(Node::new(_TupTy(tys.into()), s)), s)),
+        fields.clone(),
+    ));
+
+    let enum_ty = just(VERT_BAR)
+        .ignore_then(_attributes())
+        .then(_ident())
+        .then(struct_ty.or_not())
+        .map_with_span(|((attrs, name), inner), s| Node::new(_VariantTy { attrs, name, inner }, s))
+        .repeated()
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .map_with_span(|variants, s| Node::new(_EnumTy(variants.into()), s));
+
+    let primitive = chumsky::primitive::select(|token, s| {
+        let TokenKind::Identifier(name) = token else {
+            return None;
+        };
+        let ty = match name.as_str() {
+            "u8" => _Type::Integer(Number::new(false, Size::_8)),
+            "u16" => _Type::Integer(Number::new(false, Size::_16)),
+            "u32" => _Type::Integer(Number::new(false, Size::_32)),
+            "u64" => _Type::Integer(Number::new(false, Size::_64)),
+            "i8" => _Type::Integer(Number::new(true, Size::_8)),
+            "i16" => _Type::Integer(Number::new(true, Size::_16)),
+            "i32" => _Type::Integer(Number::new(true, Size::_32)),
+            "i64" => _Type::Integer(Number::new(true, Size::_64)),
+            "f32" => _Type::Float(Number::new(true, Size::_32)),
+            "f64" => _Type::Float(Number::new(true, Size::_64)),
+            "char" => _Type::Char,
+            _ => return None,
+        };
+        Some(Node::new(ty, s))
+    });
+
+    choice((
+        reference,
+        pointer,
+        tuple,
+        array,
+        fields.map_with_span(|ty, s| Node::new(_Type::Struct(ty), s)),
+        enum_ty.map_with_span(|ty, s| Node::new(_Type::Enum(ty), s)),
+        just(UNDERLINE).map_with_span(|_, s| Node::new(_Type::Infer, s)),
+        // `Self::Item` is a path
+        just(SELF_TY)
+            .then_ignore(just(DOUBLE_COLON).not())
+            .map_with_span(|_, s| Node::new(_Type::LocalSelf, s)),
+        just(BOOL).map_with_span(|_, s| Node::new(_Type::Boolean, s)),
+        just(FN_TY)
+            .ignore_then(signature(
+                empty().map_with_span(|_, s| Node::new(_Generics(SVec::new()), s)),
+                ty.clone(),
+            ))
+            .map_with_span(|sig, s| Node::new(_Type::FnSig(sig), s)),
+        primitive,
+        path(ty, false).map_with_span(|path, s| Node::new(_Type::Path(path), s)),
+    ))
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sexp::*, *};
+
+    #[test]
+    fn self_type_and_its_paths() {
+        assert_eq!(sexp(_type(), "Self"), "Self");
+        assert_eq!(sexp(_type(), "Self::Item"), "Self::Item");
+        assert_eq!(sexp(_type(), "&Self"), "(& Self)");
+    }
+
+    #[test]
+    fn parentheses_and_tuples() {
+        assert_eq!(sexp(_type(), "()"), "()");
+        assert_eq!(sexp(_type(), "(u8)"), "(paren u8)");
+        assert_eq!(sexp(_type(), "(u8,)"), "(tup u8)");
+        assert_eq!(sexp(_type(), "(u8, (i8,), ())"), "(tup u8, (tup i8), ())");
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(sexp(_type(), "&'a mut [u8]"), "(& 'a mut [u8])");
+        assert_eq!(sexp(_type(), "*char"), "(ptr char)");
+        assert_eq!(sexp(_type(), "[f32; 4]"), "[f32; 4]");
+        assert_eq!(sexp(_type(), "&&bool"), "(& (& bool))");
+        assert_eq!(sexp(_type(), "**u8"), "(ptr (ptr u8))");
+        assert_eq!(sexp(_type(), "**mut u8"), "(ptr (ptr mut u8))");
+        assert_eq!(sexp(_type(), "***u8"), "(ptr (ptr (ptr u8)))");
+        assert_eq!(sexp(_type(), "_"), "_");
+        assert_eq!(sexp(_type(), "a::B<'a, u8>::C"), "a::B<'a, u8>::C");
+    }
+
+    #[test]
+    fn compound_types() {
+        assert_eq!(sexp(_type(), "*mut T"), "(ptr mut T)");
+        assert_eq!(sexp(_type(), "{a: u8, b: B}"), "{a: u8, b: B}");
+        assert_eq!(sexp(_type(), "| A | B(u8)"), "(| A B(u8))");
+        assert_eq!(sexp(_type(), "Fn(u8) -> u8"), "(Fn (u8) u8)");
+        assert_eq!(sexp(_type(), "Fn()"), "(Fn () ())");
+        assert_eq!(sexp(_type(), "| A { a: u8 } | B"), "(| A{a: u8} B)");
+    }
+
+    #[test]
+    fn primitives() {
+        let ty = |source| parse_all(_type(), source).inner().cloned();
+        for (source, signed, size) in [
+            ("u8", false, Size::_8),
+            ("i16", true, Size::_16),
+            ("u32", false, Size::_32),
+            ("i64", true, Size::_64),
+        ] {
+            assert_eq!(ty(source), Some(_Type::Integer(Number::new(signed, size))));
+        }
+        assert_eq!(ty("f32"), Some(_Type::Float(Number::new(true, Size::_32))));
+        assert_eq!(ty("f64"), Some(_Type::Float(Number::new(true, Size::_64))));
+        assert_eq!(ty("bool"), Some(_Type::Boolean));
+        assert_eq!(ty("char"), Some(_Type::Char));
+        assert!(matches!(ty("u128"), Some(_Type::Path(_))));
+    }
+
+    #[test]
+    fn generics_closed_by_a_shift() {
+        assert_eq!(sexp(_type(), "A<B<C>>"), "A<B<C>>");
+        assert_eq!(sexp(_type(), "A<B<C<D>>>"), "A<B<C<D>>>");
+        assert_eq!(sexp(_type(), "A<B<C<D<E>>>>"), "A<B<C<D<E>>>>");
+        assert_eq!(sexp(_type(), "A<u8, b::C<D>>"), "A<u8, b::C<D>>");
+        assert_eq!(sexp(_type(), "A<B<C>::D<E>>"), "A<B<C>::D<E>>");
+        assert_eq!(sexp(_type(), "A<B<C>, D<E>>"), "A<B<C>, D<E>>");
+        assert_eq!(sexp(_type(), "A<B<C<D>, E>>"), "A<B<C<D>, E>>");
+        assert_eq!(sexp(_type(), "A<B<C> >"), "A<B<C>>");
+        assert_eq!(sexp(_type(), "A<B<>>"), "A<B>");
+        assert_eq!(sexp(_expr(), "a::<B<C>>(d)"), "(call a<B<C>> d)");
+        assert!(rejects(_type(), "A<B>>"));
+        assert!(rejects(_type(), "A<B<C>>>"));
+        assert!(rejects(_expr(), "a::<B>>(c)"));
+    }
+
+    #[test]
+    fn deep_nesting_parses_in_linear_time() {
+        let start = std::time::Instant::now();
+        let source = format!("{}u8{}", "A<".repeat(24), ">".repeat(24));
+        assert_eq!(sexp(_type(), &source), source);
+        let source = format!("{}u8{}", "A<".repeat(24), ">>".repeat(12));
+        assert_eq!(sexp(_type(), &source), source);
+        let source = format!("{}u8{}", "(".repeat(24), ",)".repeat(24));
+        assert!(sexp(_type(), &source).starts_with("(tup (tup"));
+        assert!(start.elapsed().as_secs() < 5);
     }
 }